@@ -0,0 +1,68 @@
+//! Alloc/dealloc throughput of `System` against `ZeroAlloc<System>` with either wiper, across sizes and thread counts.
+//!
+//! Run with `cargo bench --bench throughput`; criterion compares each run against the previous one, so regressions
+//! stand out. Filter to quantify a particular workload, e.g. `cargo bench --bench throughput -- 'threads=4/4096'`.
+
+use core::alloc::{GlobalAlloc, Layout};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::alloc::System;
+use std::hint::black_box;
+use std::sync::Barrier;
+use std::time::{Duration, Instant};
+use zeroizing_alloc::{FnPtrMemset, VolatileLoop, ZeroAlloc};
+
+static FN_PTR_MEMSET: ZeroAlloc<System, FnPtrMemset> = ZeroAlloc::with_wiper(System, FnPtrMemset);
+static VOLATILE_LOOP: ZeroAlloc<System, VolatileLoop> = ZeroAlloc::with_wiper(System, VolatileLoop);
+
+const SIZES: [usize; 5] = [16, 256, 4096, 64 << 10, 1 << 20];
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+// Each thread allocates, touches and frees a block `iters` times. Returns the wall time from when all threads are ready.
+fn churn(alloc: &(dyn GlobalAlloc + Sync), layout: Layout, threads: usize, iters: u64) -> Duration {
+    let barrier = Barrier::new(threads + 1);
+    std::thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                barrier.wait();
+                for _ in 0..iters {
+                    unsafe {
+                        let ptr = alloc.alloc(layout);
+                        // Touch the block, as a real workload would, so the wipe isn't measured on cold memory alone
+                        ptr.write(1);
+                        black_box(ptr);
+                        alloc.dealloc(ptr, layout);
+                    }
+                }
+            });
+        }
+        barrier.wait();
+        let start = Instant::now();
+        // Leaving the scope joins every thread
+        start
+    })
+    .elapsed()
+}
+
+fn throughput(c: &mut Criterion) {
+    let allocators: [(&str, &(dyn GlobalAlloc + Sync)); 3] = [
+        ("System", &System),
+        ("FnPtrMemset", &FN_PTR_MEMSET),
+        ("VolatileLoop", &VOLATILE_LOOP),
+    ];
+    for threads in THREADS {
+        let mut group = c.benchmark_group(format!("threads={threads}"));
+        for size in SIZES {
+            let layout = Layout::from_size_align(size, 8).unwrap();
+            group.throughput(Throughput::Bytes((size * threads) as u64));
+            for (name, alloc) in allocators {
+                group.bench_with_input(BenchmarkId::new(name, size), &layout, |b, &layout| {
+                    b.iter_custom(|iters| churn(alloc, layout, threads, iters))
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+use core::alloc::{GlobalAlloc, Layout};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::alloc::System;
+use std::hint::black_box;
+use zeroizing_alloc::ZeroAlloc;
+
+static WIPE_ON_FREE: ZeroAlloc<System> = ZeroAlloc::new(System);
+static WIPE_ON_ALLOC: ZeroAlloc<System> = ZeroAlloc::new(System).with_wipe_on_alloc();
+
+fn alloc_dealloc(alloc: &impl GlobalAlloc, layout: Layout) {
+    unsafe {
+        let ptr = alloc.alloc(layout);
+        black_box(ptr);
+        alloc.dealloc(ptr, layout);
+    }
+}
+
+fn wipe_on_alloc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("wipe_on_alloc");
+    for size in [64, 4096, 1 << 20] {
+        let layout = Layout::from_size_align(size, 8).unwrap();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("wipe_on_free", size),
+            &layout,
+            |b, &layout| b.iter(|| alloc_dealloc(&WIPE_ON_FREE, layout)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("wipe_on_alloc", size),
+            &layout,
+            |b, &layout| b.iter(|| alloc_dealloc(&WIPE_ON_ALLOC, layout)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, wipe_on_alloc);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use zeroizing_alloc::{FnPtrMemset, VolatileLoop, Wiper};
+
+fn bench_wiper(c: &mut Criterion, name: &str, wiper: &impl Wiper) {
+    let mut group = c.benchmark_group("wipers");
+    for size in [4 << 10, 256 << 10, 4 << 20, 64 << 20] {
+        let mut buf = vec![0xAAu8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(BenchmarkId::new(name, size), |b| {
+            b.iter(|| unsafe { wiper.wipe(black_box(buf.as_mut_ptr()), size, 0) })
+        });
+    }
+    group.finish();
+}
+
+fn wipers(c: &mut Criterion) {
+    bench_wiper(c, "FnPtrMemset", &FnPtrMemset);
+    bench_wiper(c, "VolatileLoop", &VolatileLoop);
+    #[cfg(target_arch = "x86_64")]
+    bench_wiper(c, "NonTemporal", &zeroizing_alloc::NonTemporal::new());
+}
+
+criterion_group!(benches, wipers);
+criterion_main!(benches);
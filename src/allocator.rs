@@ -0,0 +1,155 @@
+// `core::alloc::Allocator` and `allocator_api2::alloc::Allocator` share the same shape, so one
+// implementation is stamped out for whichever of the two are enabled.
+macro_rules! impl_allocator {
+    ($($alloc:ident)::+) => {
+        use $($alloc)::+::{AllocError, Allocator, Layout};
+        use core::ptr::NonNull;
+
+        use crate::{Wiper, ZeroAlloc};
+
+        // Resizes in place when the inner allocator supports it and the alignment is unchanged.
+        //
+        // SAFETY: callers must uphold the contract of `Allocator::grow` or `Allocator::shrink`
+        #[inline]
+        unsafe fn resize_in_place<A: Allocator, W: Wiper>(
+            this: &ZeroAlloc<A, W>,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new_layout: Layout,
+        ) -> bool {
+            let Some((can_resize, resize)) = this.resize_in_place else {
+                return false;
+            };
+            // Zero-sized blocks are dangling pointers the inner allocator never handed out, and it can't resize a block to
+            // nothing either, so either end being zero-sized always takes a fresh allocation
+            if old_layout.align() != new_layout.align() || old_layout.size() == 0 || new_layout.size() == 0 {
+                return false;
+            }
+            if new_layout.size() < old_layout.size() {
+                // Same as `GlobalAlloc::realloc`: the truncated tail is wiped before the inner allocator takes it back,
+                // but only once the shrink can no longer fail, as `Err` must leave the block untouched.
+                if !can_resize(&this.inner, ptr.as_ptr(), old_layout, new_layout.size()) {
+                    return false;
+                }
+                this.zero(ptr.as_ptr(), old_layout, new_layout.size(), false);
+            }
+            resize(&this.inner, ptr.as_ptr(), old_layout, new_layout.size())
+        }
+
+        // Moves the block into a fresh allocation, then wipes and releases the old one.
+        //
+        // SAFETY: callers must uphold the contract of `Allocator::grow` or `Allocator::shrink`
+        #[inline]
+        unsafe fn move_block<A: Allocator, W: Wiper>(
+            this: &ZeroAlloc<A, W>,
+            ptr: NonNull<u8>,
+            old_layout: Layout,
+            new: Result<NonNull<[u8]>, AllocError>,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            let new = new?;
+            let len = core::cmp::min(old_layout.size(), new.len());
+            core::ptr::copy_nonoverlapping(ptr.as_ptr(), new.cast::<u8>().as_ptr(), len);
+            #[cfg(feature = "selective")]
+            this.inherit(ptr.as_ptr(), new.cast::<u8>().as_ptr());
+            this.deallocate(ptr, old_layout);
+            #[cfg(feature = "stats")]
+            this.stats.moved();
+            Ok(new)
+        }
+
+        // SAFETY: wrapper for the inner allocator, zeroizes on free but otherwise re-uses its logic
+        unsafe impl<A: Allocator, W: Wiper> Allocator for ZeroAlloc<A, W> {
+            #[inline]
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                let block = if self.wipe_on_alloc {
+                    self.inner.allocate_zeroed(layout)?
+                } else {
+                    self.inner.allocate(layout)?
+                };
+                #[cfg(feature = "selective")]
+                self.track(block.cast::<u8>().as_ptr());
+                #[cfg(feature = "stats")]
+                self.stats.allocated(block.cast::<u8>().as_ptr());
+                Ok(block)
+            }
+
+            #[inline]
+            fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                let block = self.inner.allocate_zeroed(layout)?;
+                #[cfg(feature = "selective")]
+                self.track(block.cast::<u8>().as_ptr());
+                #[cfg(feature = "stats")]
+                self.stats.allocated(block.cast::<u8>().as_ptr());
+                Ok(block)
+            }
+
+            #[inline]
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                // Blocks allocated through `Allocator` never get canaries
+                self.zero(ptr.as_ptr(), layout, 0, false);
+                #[cfg(feature = "selective")]
+                self.untrack(ptr.as_ptr());
+                self.inner.deallocate(ptr, layout);
+                #[cfg(feature = "stats")]
+                self.stats.deallocated();
+            }
+
+            #[inline]
+            unsafe fn grow(
+                &self,
+                ptr: NonNull<u8>,
+                old_layout: Layout,
+                new_layout: Layout,
+            ) -> Result<NonNull<[u8]>, AllocError> {
+                if resize_in_place(self, ptr, old_layout, new_layout) {
+                    if self.wipe_on_alloc {
+                        let grown = new_layout.size() - old_layout.size();
+                        ptr.as_ptr().add(old_layout.size()).write_bytes(0, grown);
+                    }
+                    return Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+                }
+                move_block(self, ptr, old_layout, self.allocate(new_layout))
+            }
+
+            #[inline]
+            unsafe fn grow_zeroed(
+                &self,
+                ptr: NonNull<u8>,
+                old_layout: Layout,
+                new_layout: Layout,
+            ) -> Result<NonNull<[u8]>, AllocError> {
+                if resize_in_place(self, ptr, old_layout, new_layout) {
+                    let grown = new_layout.size() - old_layout.size();
+                    ptr.as_ptr().add(old_layout.size()).write_bytes(0, grown);
+                    return Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+                }
+                move_block(self, ptr, old_layout, self.allocate_zeroed(new_layout))
+            }
+
+            #[inline]
+            unsafe fn shrink(
+                &self,
+                ptr: NonNull<u8>,
+                old_layout: Layout,
+                new_layout: Layout,
+            ) -> Result<NonNull<[u8]>, AllocError> {
+                if resize_in_place(self, ptr, old_layout, new_layout) {
+                    return Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+                }
+                move_block(self, ptr, old_layout, self.allocate(new_layout))
+            }
+        }
+    };
+}
+
+#[cfg(feature = "allocator_api")]
+mod allocator_api {
+    impl_allocator!(core::alloc);
+}
+
+// Note: if `allocator-api2` is built with its own "nightly" feature it re-exports `core::alloc::Allocator`,
+// in which case only one of the two features may be enabled.
+#[cfg(feature = "allocator-api2")]
+mod allocator_api2 {
+    impl_allocator!(allocator_api2::alloc);
+}
@@ -0,0 +1,113 @@
+use crate::{Wiper, ZeroAlloc};
+use core::alloc::{GlobalAlloc, Layout};
+
+/// Called with a block and its layout when its redzones were found overwritten, see [`ZeroAlloc::with_canary_hook`].
+///
+/// If it returns, the redzones are wiped and the block is freed as usual.
+pub type CanaryHook = fn(*mut u8, Layout);
+
+/// Bytes of canaries after each block, and at least before it.
+const REDZONE: usize = 16;
+
+#[cfg(feature = "std")]
+fn abort_on_corruption(_ptr: *mut u8, _layout: Layout) {
+    std::process::abort();
+}
+
+// Unwinding out of an allocator is undefined behavior, so a panicking hook is turned into an abort: panicking again while
+// unwinding always aborts.
+struct AbortOnUnwind;
+
+impl Drop for AbortOnUnwind {
+    fn drop(&mut self) {
+        panic!("a canary hook panicked inside the allocator");
+    }
+}
+
+// The canary byte of the block padded out to `outer`, which varies between blocks so that an overflow copying
+// a neighbour's redzone is still caught.
+fn canary(outer: *mut u8) -> u8 {
+    (outer.addr() >> 4) as u8 ^ 0xA5
+}
+
+// Returns the offset of the block within its padded layout, and the padded layout itself.
+fn padded(layout: Layout) -> Option<(usize, Layout)> {
+    // Keeps the block aligned
+    let front = layout.align().max(REDZONE);
+    let size = front.checked_add(layout.size())?.checked_add(REDZONE)?;
+    Some((front, Layout::from_size_align(size, layout.align()).ok()?))
+}
+
+impl<Alloc, W> ZeroAlloc<Alloc, W> {
+    /// Surrounds each block with canary bytes, aborting the process if they were overwritten by the time it's freed.
+    ///
+    /// Overruns into neighbouring blocks are how secrets leak between them, and this catches them on free at the cost
+    /// of at least 32 bytes per block. These blocks always move on `realloc`, and
+    /// [`with_usable_size`](Self::with_usable_size) has no effect on them, as they no longer extend to the end of what the
+    /// inner allocator handed out. This only applies to [`GlobalAlloc`]: blocks allocated through `Allocator` get no
+    /// canaries, and keep having their slack wiped.
+    ///
+    /// Requires feature "std" to abort; without it, use [`with_canary_hook`](Self::with_canary_hook).
+    #[cfg(feature = "std")]
+    pub const fn with_canaries(self) -> Self {
+        self.with_canary_hook(abort_on_corruption)
+    }
+
+    /// Surrounds each block with canary bytes like `with_canaries`, but calls `hook` instead of aborting when they were
+    /// overwritten, e.g. to report the corruption.
+    ///
+    /// Should `hook` panic, the process aborts: unwinding out of an allocator is undefined behavior.
+    pub const fn with_canary_hook(mut self, hook: CanaryHook) -> Self {
+        self.canaries = Some(hook);
+        self
+    }
+}
+
+impl<Alloc: GlobalAlloc, W: Wiper> ZeroAlloc<Alloc, W> {
+    // Allocates the block from the inner allocator, between two redzones filled with its canary byte.
+    pub(crate) unsafe fn alloc_with_redzones(&self, layout: Layout, zeroed: bool) -> *mut u8 {
+        let Some((front, outer_layout)) = padded(layout) else {
+            return core::ptr::null_mut();
+        };
+        let outer = if zeroed {
+            self.inner.alloc_zeroed(outer_layout)
+        } else {
+            self.inner.alloc(outer_layout)
+        };
+        if outer.is_null() {
+            return outer;
+        }
+        let ptr = outer.add(front);
+        outer.write_bytes(canary(outer), front);
+        ptr.add(layout.size()).write_bytes(canary(outer), REDZONE);
+        ptr
+    }
+
+    // Hands the block back to the inner allocator, calling `hook` first if its redzones were overwritten.
+    pub(crate) unsafe fn dealloc_with_redzones(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        hook: CanaryHook,
+    ) {
+        // SAFETY: `inner_alloc` succeeded in padding this layout
+        let (front, outer_layout) = padded(layout).unwrap_unchecked();
+        let outer = ptr.sub(front);
+        let back = ptr.add(layout.size());
+        let byte = canary(outer);
+        let intact = |redzone: *mut u8, len| {
+            core::slice::from_raw_parts(redzone, len)
+                .iter()
+                .all(|&b| b == byte)
+        };
+        if !intact(outer, front) || !intact(back, REDZONE) {
+            let guard = AbortOnUnwind;
+            hook(ptr, layout);
+            core::mem::forget(guard);
+            // An overrun may have left secrets in the redzones
+            self.wiper.wipe(outer, front, self.pattern);
+            self.wiper.wipe(back, REDZONE, self.pattern);
+        }
+        self.inner.dealloc(outer, outer_layout);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::spin::SpinLock;
+use crate::{Wiper, ZeroAlloc};
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, OnceLock, PoisonError};
+use std::thread::{self, Thread};
+
+/// A bounded queue of freed blocks waiting to be wiped and released by a background worker.
+///
+/// Set up through [`ZeroAlloc::with_deferred_wipe`] and drained by the thread started with
+/// [`ZeroAlloc::spawn_deferred_wiper`]. Queued blocks stay reserved until the worker hands them back to the inner allocator.
+///
+/// The queue holds up to `N` blocks, as it can't grow from inside the allocator. A few dozen are plenty for the occasional
+/// large free it's meant for: should frees outpace the worker, the excess is simply wiped synchronously.
+///
+/// ```
+/// static QUEUE: zeroizing_alloc::DeferQueue<64> = zeroizing_alloc::DeferQueue::new();
+/// ```
+pub struct DeferQueue<const N: usize> {
+    ring: SpinLock<Ring<N>>,
+    state: State,
+}
+
+struct Ring<const N: usize> {
+    blocks: [Option<(*mut u8, Layout)>; N],
+    head: usize,
+    len: usize,
+}
+
+// The part of a queue which doesn't depend on its capacity
+pub(crate) struct State {
+    pending: AtomicUsize,
+    // The worker, and the address of the allocator it releases blocks to
+    worker: OnceLock<(Thread, usize)>,
+    // Notified whenever `pending` drops to zero, for `flush_deferred` to wait on
+    idle: Mutex<()>,
+    drained: Condvar,
+}
+
+// SAFETY: the ring is only accessed while holding its lock, and the blocks it points to are owned by the queue
+unsafe impl<const N: usize> Sync for DeferQueue<N> {}
+
+impl<const N: usize> DeferQueue<N> {
+    /// Creates an empty queue, typically kept in a `static` so it can be handed to [`ZeroAlloc::with_deferred_wipe`].
+    pub const fn new() -> Self {
+        Self {
+            ring: SpinLock::new(Ring {
+                blocks: [None; N],
+                head: 0,
+                len: 0,
+            }),
+            state: State {
+                pending: AtomicUsize::new(0),
+                worker: OnceLock::new(),
+                idle: Mutex::new(()),
+                drained: Condvar::new(),
+            },
+        }
+    }
+
+    /// Returns how many queued blocks have not been handed back to the inner allocator yet.
+    pub fn pending(&self) -> usize {
+        self.state.pending()
+    }
+}
+
+impl<const N: usize> Default for DeferQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Lets `ZeroAlloc` refer to queues of any capacity, without a parameter of its own for it.
+pub(crate) trait Queue: Sync {
+    fn state(&self) -> &State;
+
+    // Adds the block to the ring, unless it's full or another thread holds the lock.
+    fn try_push(&self, ptr: *mut u8, layout: Layout) -> bool;
+
+    fn pop(&self) -> Option<(*mut u8, Layout)>;
+}
+
+impl<const N: usize> Queue for DeferQueue<N> {
+    fn state(&self) -> &State {
+        &self.state
+    }
+
+    fn try_push(&self, ptr: *mut u8, layout: Layout) -> bool {
+        self.ring.try_with(|ring| {
+            if ring.len == N {
+                return false;
+            }
+            ring.blocks[(ring.head + ring.len) % N] = Some((ptr, layout));
+            ring.len += 1;
+            self.state.pending.fetch_add(1, Ordering::Relaxed);
+            true
+        }) == Some(true)
+    }
+
+    fn pop(&self) -> Option<(*mut u8, Layout)> {
+        self.ring.with(|ring| {
+            // Checked first, as a queue with no room at all has no slot to look at
+            if ring.len == 0 {
+                return None;
+            }
+            let block = ring.blocks[ring.head].take();
+            ring.head = (ring.head + 1) % N;
+            ring.len -= 1;
+            block
+        })
+    }
+}
+
+impl State {
+    fn pending(&self) -> usize {
+        self.pending.load(Ordering::Acquire)
+    }
+
+    // Returns the worker, if one was started by the allocator at `owner`. Blocks must go back to the allocator they came
+    // from, so a queue shared by several allocators only ever holds blocks of the one which started its worker.
+    fn worker(&self, owner: usize) -> Option<&Thread> {
+        match self.worker.get() {
+            Some((worker, worker_owner)) if *worker_owner == owner => Some(worker),
+            _ => None,
+        }
+    }
+
+    // Marks a queued block as handed back, waking up flushes once none are left.
+    fn released(&self) {
+        if self.pending.fetch_sub(1, Ordering::Release) == 1 {
+            // Taking the lock orders this between a flush checking `pending` and going to sleep, so it can't be missed
+            drop(self.idle.lock().unwrap_or_else(PoisonError::into_inner));
+            self.drained.notify_all();
+        }
+    }
+}
+
+impl dyn Queue {
+    // Queues the block, unless there's no worker to drain it, the queue is full, or another thread holds the lock.
+    // `dealloc` must never wait on the worker, so it falls back to wiping synchronously instead.
+    fn push(&self, owner: usize, ptr: *mut u8, layout: Layout) -> bool {
+        let Some(worker) = self.state().worker(owner) else {
+            return false;
+        };
+        let queued = self.try_push(ptr, layout);
+        if queued {
+            worker.unpark();
+        }
+        queued
+    }
+}
+
+impl<Alloc, W> ZeroAlloc<Alloc, W> {
+    /// Hands allocations of at least `min_size` bytes to a background worker on free, which wipes and releases them.
+    ///
+    /// Frees stay synchronous until [`spawn_deferred_wiper`](Self::spawn_deferred_wiper) has started the worker, and
+    /// whenever `queue` is full or busy. This only applies to [`GlobalAlloc::dealloc`] and the blocks `realloc` moves away from.
+    /// Should several allocators share `queue`, only the one which started its worker defers frees.
+    pub const fn with_deferred_wipe<const N: usize>(
+        mut self,
+        queue: &'static DeferQueue<N>,
+        min_size: usize,
+    ) -> Self {
+        let queue: &'static dyn Queue = queue;
+        self.deferred = Some((queue, min_size));
+        self
+    }
+
+    // Queues the block if it's large enough to be deferred, returning whether it was.
+    #[inline]
+    pub(crate) fn defer(&self, ptr: *mut u8, layout: Layout) -> bool {
+        match self.deferred {
+            Some((queue, min_size)) => {
+                layout.size() >= min_size
+                    && layout.size() <= self.max_size
+                    && !crate::scope::wiping_suppressed()
+                    && queue.push(self.address(), ptr, layout)
+            }
+            None => false,
+        }
+    }
+
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+impl<Alloc, W> ZeroAlloc<Alloc, W>
+where
+    Alloc: GlobalAlloc + Sync,
+    W: Wiper + Sync,
+{
+    /// Starts the background thread which wipes and releases the blocks queued through [`with_deferred_wipe`](Self::with_deferred_wipe).
+    ///
+    /// Frees are deferred once this returns, and the worker runs for the rest of the process. Calling this again, or without
+    /// a queue configured, does nothing.
+    pub fn spawn_deferred_wiper(&'static self) -> std::io::Result<()> {
+        let Some((queue, _)) = self.deferred else {
+            return Ok(());
+        };
+        let state = queue.state();
+        if state.worker.get().is_some() {
+            return Ok(());
+        }
+        let worker = thread::Builder::new()
+            .name("zeroizing-alloc-wiper".into())
+            .spawn(move || {
+                // Parks until the spawning thread has registered a worker, which is someone else's if it lost a race
+                // against another call
+                let worker = loop {
+                    match state.worker.get() {
+                        Some((worker, _)) => break worker,
+                        None => thread::park(),
+                    }
+                };
+                if worker.id() != thread::current().id() {
+                    return;
+                }
+                loop {
+                    self.release_queued(queue);
+                    thread::park();
+                }
+            })?;
+        // Frees are deferred as soon as the worker is registered, so there's no need to wait for it to start
+        let _ = state.worker.set((worker.thread().clone(), self.address()));
+        worker.thread().unpark();
+        Ok(())
+    }
+
+    /// Wipes and releases every queued block on the calling thread, then waits for any the worker is still busy with.
+    ///
+    /// Useful before exiting, since the worker may still hold blocks when `main` returns.
+    pub fn flush_deferred(&self) {
+        if let Some((queue, _)) = self.deferred {
+            let state = queue.state();
+            if state.worker(self.address()).is_none() {
+                return; // Nothing was queued by this allocator
+            }
+            self.release_queued(queue);
+            let mut idle = state.idle.lock().unwrap_or_else(PoisonError::into_inner);
+            while state.pending() != 0 {
+                idle = state
+                    .drained
+                    .wait(idle)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+    }
+
+    fn release_queued(&self, queue: &dyn Queue) {
+        while let Some((ptr, layout)) = queue.pop() {
+            // SAFETY: the block was queued by `dealloc`, which got it from the caller along with its layout
+            unsafe {
+                self.zero(ptr, layout, 0, self.redzones());
+                #[cfg(feature = "selective")]
+                self.untrack(ptr);
+                self.inner_dealloc(ptr, layout);
+            }
+            #[cfg(feature = "stats")]
+            self.stats.deallocated();
+            queue.state().released();
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::pages::{page_size, PageAlloc, PageState, PageTable};
+use core::alloc::{GlobalAlloc, Layout};
+
+/// Inner allocator excluding the pages backing each block from core dumps with `MADV_DONTDUMP`.
+///
+/// A crashing service dumps its live secrets long before they're freed and wiped. Pages are included in dumps again once
+/// the last block on them is freed, after a wrapping [`ZeroAlloc`](crate::ZeroAlloc) has wiped it:
+///
+/// ```
+/// use std::alloc::System;
+/// use zeroizing_alloc::{DontDumpAlloc, ZeroAlloc};
+///
+/// static SECRETS: ZeroAlloc<DontDumpAlloc<System>> = ZeroAlloc::new(DontDumpAlloc::new(System));
+/// ```
+///
+/// Pages are shared with neighbouring blocks, which are left out of dumps along with them, and every `DontDumpAlloc`
+/// counts blocks in the same table: a page is only included again once the last block on it is freed, whichever allocator
+/// it came from. Pages which can't be marked, or tracked once more than a few thousand are marked at once, are counted in
+/// [`errors`](Self::errors). Once a page couldn't be tracked, pages are no longer included in dumps again, as other blocks
+/// may still need them excluded.
+pub struct DontDumpAlloc<Alloc>(PageAlloc<Alloc, Advice>);
+
+// Excludes pages from dumps with `madvise`, and has them wiped in forked children if `wipe_on_fork` is set
+struct Advice {
+    wipe_on_fork: bool,
+}
+
+// The blocks on each excluded page, across every `DontDumpAlloc`
+static EXCLUDED: PageTable = PageTable::new();
+
+/// Allocators whose blocks have pages to themselves, such as `GuardedAlloc` (feature "guard-pages").
+///
+/// # Safety
+///
+/// Every page spanned by a block must hold nothing but that block for as long as it is allocated.
+pub unsafe trait DedicatedPages {}
+
+impl PageState for Advice {
+    fn table() -> &'static PageTable {
+        &EXCLUDED
+    }
+
+    unsafe fn apply(&self, run: *mut u8, len: usize, exclude: bool) -> usize {
+        let advise = |advice| {
+            if libc::madvise(run.cast(), len * page_size(), advice) != 0 {
+                len
+            } else {
+                0
+            }
+        };
+        match (exclude, self.wipe_on_fork) {
+            (true, false) => advise(libc::MADV_DONTDUMP),
+            (true, true) => advise(libc::MADV_DONTDUMP) + advise(libc::MADV_WIPEONFORK),
+            (false, false) => advise(libc::MADV_DODUMP),
+            (false, true) => advise(libc::MADV_DODUMP) + advise(libc::MADV_KEEPONFORK),
+        }
+    }
+}
+
+impl<Alloc> DontDumpAlloc<Alloc> {
+    /// Wraps `inner`, excluding the pages of every block it hands out from core dumps.
+    pub const fn new(inner: Alloc) -> Self {
+        Self(PageAlloc::new(
+            inner,
+            Advice {
+                wipe_on_fork: false,
+            },
+        ))
+    }
+
+    /// Also marks pages with `MADV_WIPEONFORK` (Linux 4.14+), so forked children see them zeroed.
+    ///
+    /// The whole page reads as zeros in the child, which is why this requires blocks to have their pages to themselves.
+    pub const fn with_wipe_on_fork(mut self) -> Self
+    where
+        Alloc: DedicatedPages,
+    {
+        self.0.state.wipe_on_fork = true;
+        self
+    }
+
+    /// Returns a reference to the wrapped allocator.
+    pub const fn inner(&self) -> &Alloc {
+        &self.0.inner
+    }
+
+    /// Returns how many pages backing live blocks are tracked, across every `DontDumpAlloc` in the process.
+    ///
+    /// This includes pages which failed to be marked, as they are still counted until their last block is freed. Once a page
+    /// couldn't be tracked, pages staying excluded after their last block was freed are no longer counted.
+    pub fn marked_pages(&self) -> usize {
+        EXCLUDED.len()
+    }
+
+    /// Returns how many pages could not be marked so far.
+    pub fn errors(&self) -> usize {
+        self.0.errors()
+    }
+}
+
+// SAFETY: forwards to `PageAlloc`
+unsafe impl<Alloc: GlobalAlloc> GlobalAlloc for DontDumpAlloc<Alloc> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+}
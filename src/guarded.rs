@@ -0,0 +1,102 @@
+use crate::pages::page_size;
+use crate::{FnPtrMemset, UsableSize, Wiper};
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+
+/// Allocator placing every allocation on its own pages, between two inaccessible guard pages.
+///
+/// Each block ends right at the trailing guard page (give or take its alignment), so overflows fault on the first byte past
+/// it instead of running into a neighbouring secret. Freed blocks are wiped and unmapped, so a use-after-free faults
+/// too, and the pages go back to the kernel holding zeros.
+///
+/// It can be used on its own, or as the inner allocator of a [`ZeroAlloc`](crate::ZeroAlloc) (to count its work, or fill
+/// freed blocks with a pattern first):
+///
+/// ```
+/// use zeroizing_alloc::{GuardedAlloc, ZeroAlloc};
+///
+/// static SECRETS: ZeroAlloc<GuardedAlloc> = ZeroAlloc::new(GuardedAlloc).with_usable_size();
+/// ```
+///
+/// Every allocation costs at least three pages of address space and a system call, so this is meant for the few blocks
+/// holding key material rather than as a global allocator. Alignments above the page size are not supported.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GuardedAlloc;
+
+impl GuardedAlloc {
+    // Returns the start and length of the accessible pages backing a block of `layout` at `ptr`.
+    fn data_pages(ptr: *mut u8, layout: Layout) -> (*mut u8, usize) {
+        let page = page_size();
+        let start = ptr.map_addr(|addr| addr & !(page - 1));
+        (start, layout.size().next_multiple_of(page))
+    }
+}
+
+// SAFETY: blocks are backed by fresh, private mappings which are only unmapped in `dealloc`
+unsafe impl GlobalAlloc for GuardedAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let page = page_size();
+        if layout.align() > page {
+            return ptr::null_mut();
+        }
+        let Some(len) = layout
+            .size()
+            .checked_next_multiple_of(page)
+            .and_then(|data| data.checked_add(2 * page))
+        else {
+            return ptr::null_mut();
+        };
+
+        let map = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        if map == libc::MAP_FAILED {
+            return ptr::null_mut();
+        }
+        let data = map.cast::<u8>().add(page);
+        if libc::mprotect(
+            data.cast(),
+            len - 2 * page,
+            libc::PROT_READ | libc::PROT_WRITE,
+        ) != 0
+        {
+            libc::munmap(map, len);
+            return ptr::null_mut();
+        }
+
+        // Push the block against the trailing guard page, as far as its alignment allows
+        let end = data.add(len - 2 * page);
+        end.sub(layout.size())
+            .map_addr(|addr| addr & !(layout.align() - 1))
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // Fresh anonymous mappings are already zeroed
+        self.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let page = page_size();
+        let (data, len) = Self::data_pages(ptr, layout);
+        // The kernel only clears unmapped pages once it hands them out again
+        FnPtrMemset.wipe(data, len, 0);
+        libc::munmap(data.sub(page).cast(), len + 2 * page);
+    }
+}
+
+// SAFETY: the bytes between the block and the trailing guard page belong to its mapping
+unsafe impl UsableSize for GuardedAlloc {
+    unsafe fn usable_size(&self, ptr: *mut u8, layout: Layout) -> usize {
+        let (data, len) = Self::data_pages(ptr, layout);
+        data.add(len).offset_from(ptr) as usize
+    }
+}
+
+// SAFETY: every block gets a mapping of its own
+#[cfg(all(feature = "dont-dump", any(target_os = "linux", target_os = "android")))]
+unsafe impl crate::DedicatedPages for GuardedAlloc {}
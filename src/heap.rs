@@ -0,0 +1,237 @@
+use crate::pages::page_size;
+use crate::spin::SpinLock;
+use crate::{FnPtrMemset, UsableSize, Wiper};
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// Allocator serving blocks from a virtual memory region of its own, keeping key material apart from the general heap.
+///
+/// The region is reserved on first use, and carved into power-of-two size classes with a free list each. Blocks are wiped
+/// when freed, so every block handed out reads as zeros. The whole region can be [locked](Self::lock) into RAM,
+/// [excluded from core dumps](Self::exclude_from_dumps), or [wiped at once](Self::wipe_all), e.g. when shutting down.
+///
+/// Since the heap zeroes each block's whole size class on free, it needs no [`ZeroAlloc`](crate::ZeroAlloc) around it.
+/// Wrapping it in one is still how to count its allocations with feature "stats":
+///
+/// ```
+/// use zeroizing_alloc::{SecretHeap, ZeroAlloc};
+///
+/// static SECRETS: ZeroAlloc<SecretHeap> = ZeroAlloc::new(SecretHeap::new(1 << 20));
+/// ```
+///
+/// A block is aligned to its size class, up to a page, so allocations asking for more than page alignment fail. Behind a
+/// [`RoutingAlloc`](crate::RoutingAlloc), the heap can be kept to the small layouts keys and tokens live in.
+pub struct SecretHeap {
+    // Null until the region is reserved, which is done outside of the lock
+    base: AtomicPtr<u8>,
+    size: usize,
+    state: SpinLock<State>,
+}
+
+struct State {
+    // Offset of the first byte never handed out
+    next: usize,
+    // Head of the free list of each size class, indexed by its log2. Freed blocks link to the next one in their first word.
+    free: [*mut u8; usize::BITS as usize],
+}
+
+// SAFETY: `state` is only accessed while holding its lock, and the blocks it points to belong to the region
+unsafe impl Sync for SecretHeap {}
+// SAFETY: the region is owned by the heap, and only reachable through it
+unsafe impl Send for SecretHeap {}
+
+const MIN_CLASS: usize = core::mem::size_of::<*mut u8>();
+
+impl SecretHeap {
+    /// Creates a heap over a region of `size` bytes, reserved (but not committed) on first use.
+    pub const fn new(size: usize) -> Self {
+        Self {
+            base: AtomicPtr::new(ptr::null_mut()),
+            size,
+            state: SpinLock::new(State {
+                next: 0,
+                free: [ptr::null_mut(); usize::BITS as usize],
+            }),
+        }
+    }
+
+    /// Locks the region into RAM with `mlock`, so its contents are never written to swap.
+    ///
+    /// This commits the whole region, which must fit within `RLIMIT_MEMLOCK`. Returns the `errno` on failure.
+    pub fn lock(&self) -> Result<(), i32> {
+        self.with_region(|base, size| {
+            // SAFETY: the region is mapped for as long as the heap lives
+            unsafe { libc::mlock(base.cast(), size) }
+        })
+    }
+
+    /// Excludes the region from core dumps with `MADV_DONTDUMP`. Returns the `errno` on failure.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn exclude_from_dumps(&self) -> Result<(), i32> {
+        self.with_region(|base, size| {
+            // SAFETY: the region is mapped for as long as the heap lives
+            unsafe { libc::madvise(base.cast(), size, libc::MADV_DONTDUMP) }
+        })
+    }
+
+    /// Wipes every block ever handed out, live or not, and takes them all back.
+    ///
+    /// # Safety
+    ///
+    /// Every block allocated so far is freed by this: none may be used or deallocated afterwards.
+    pub unsafe fn wipe_all(&self) {
+        let base = self.base.load(Ordering::Acquire);
+        self.state.with(|state| {
+            if !base.is_null() {
+                FnPtrMemset.wipe(base, state.next, 0);
+            }
+            state.next = 0;
+            state.free = [ptr::null_mut(); usize::BITS as usize];
+        });
+    }
+
+    fn with_region(&self, f: impl FnOnce(*mut u8, usize) -> libc::c_int) -> Result<(), i32> {
+        let base = self.reserve().ok_or_else(errno)?;
+        match f(base, self.size) {
+            0 => Ok(()),
+            _ => Err(errno()),
+        }
+    }
+
+    // Returns the start of the region, reserving it on first use. Threads racing to reserve it each map a region, and all
+    // but the first to publish theirs unmap it again.
+    fn reserve(&self) -> Option<*mut u8> {
+        let base = self.base.load(Ordering::Acquire);
+        if !base.is_null() {
+            return Some(base);
+        }
+        // SAFETY: a fresh anonymous mapping doesn't alias anything
+        let mapped = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                self.size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | MAP_NORESERVE,
+                -1,
+                0,
+            )
+        };
+        if mapped == libc::MAP_FAILED {
+            return None;
+        }
+        let mapped = mapped.cast::<u8>();
+        match self.base.compare_exchange(
+            ptr::null_mut(),
+            mapped,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => Some(mapped),
+            Err(base) => {
+                // SAFETY: the mapping was never handed out
+                unsafe { libc::munmap(mapped.cast(), self.size) };
+                Some(base)
+            }
+        }
+    }
+
+    // Returns the size class serving `layout`, as its log2.
+    fn class(layout: Layout) -> Option<u32> {
+        if layout.align() > page_size() {
+            return None;
+        }
+        let size = layout.size().max(layout.align()).max(MIN_CLASS);
+        size.checked_next_power_of_two().map(usize::trailing_zeros)
+    }
+}
+
+// FreeBSD and DragonFly never reserve swap for anonymous mappings upfront, so they have no flag to opt out of it
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+const MAP_NORESERVE: libc::c_int = 0;
+#[cfg(not(any(target_os = "freebsd", target_os = "dragonfly")))]
+use libc::MAP_NORESERVE;
+
+fn errno() -> i32 {
+    // SAFETY: `errno` is thread-local, and was just set by the failed call
+    unsafe {
+        #[cfg(any(target_os = "linux", target_os = "dragonfly"))]
+        let errno = libc::__errno_location();
+        #[cfg(any(target_vendor = "apple", target_os = "freebsd"))]
+        let errno = libc::__error();
+        #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))]
+        let errno = libc::__errno();
+        #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+        let errno = libc::___errno();
+        #[cfg(target_os = "haiku")]
+        let errno = libc::_errnop();
+        *errno
+    }
+}
+
+impl Drop for SecretHeap {
+    fn drop(&mut self) {
+        let base = *self.base.get_mut();
+        if !base.is_null() {
+            let next = self.state.with(|state| state.next);
+            // SAFETY: nothing can be borrowed from the heap anymore
+            unsafe {
+                FnPtrMemset.wipe(base, next, 0);
+                libc::munmap(base.cast(), self.size);
+            }
+        }
+    }
+}
+
+// SAFETY: blocks are disjoint slots of the region, aligned to their class size (or the page size, whichever is smaller)
+unsafe impl GlobalAlloc for SecretHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(class) = Self::class(layout) else {
+            return ptr::null_mut();
+        };
+        let Some(base) = self.reserve() else {
+            return ptr::null_mut();
+        };
+        self.state.with(|state| {
+            let head = state.free[class as usize];
+            if !head.is_null() {
+                // Clear the link, so the block is handed out fully wiped
+                state.free[class as usize] = head.cast::<*mut u8>().replace(ptr::null_mut());
+                return head;
+            }
+
+            let size = 1 << class;
+            let start = state.next.next_multiple_of(size.min(page_size()));
+            match start.checked_add(size) {
+                Some(end) if end <= self.size => {
+                    state.next = end;
+                    base.add(start)
+                }
+                _ => ptr::null_mut(),
+            }
+        })
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // Fresh blocks come zeroed from the kernel, and recycled ones were wiped when freed
+        self.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let Some(class) = Self::class(layout) else {
+            return;
+        };
+        FnPtrMemset.wipe(ptr, 1 << class, 0);
+        self.state.with(|state| {
+            ptr.cast::<*mut u8>().write(state.free[class as usize]);
+            state.free[class as usize] = ptr;
+        });
+    }
+}
+
+// SAFETY: each block spans its whole size class
+unsafe impl UsableSize for SecretHeap {
+    unsafe fn usable_size(&self, _ptr: *mut u8, layout: Layout) -> usize {
+        Self::class(layout).map_or(layout.size(), |class| 1 << class)
+    }
+}
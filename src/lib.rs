@@ -1,96 +1,488 @@
 #![no_std]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 
 //! An example crate showing how to safely and performantly zero out all heap allocations in a process.
 //!
 //!
 //! This crates makes the following changes from common zeroizing alloc implementations:
 //!
-//! - Introduce a faster zeroization implementation (original kept behind feature "reference_impl" for perf testing)
+//! - Introduce a faster zeroization implementation (original kept as [`VolatileLoop`], the default behind feature "reference_impl", for perf testing)
 //! - Fix a potential casting bug
 //! - Remove unit tests: although passing locally, they trigger UAF and UB, leading to inconsistency, which we don't want.
 //!     - Used `MIRIFLAGS="-Zmiri-ignore-leaks" cargo +nightly miri test -p op-alloc`
 //!
+//! [`ZeroAlloc`] is configured through its `with_*` methods, and the optional features are listed in `Cargo.toml`.
+//!
 //! <https://rust.godbolt.org> was a tool used to partially verify that zeroization will NOT be optimized out at `-Copt-level=3`
 
+#[cfg(feature = "std")]
+extern crate std;
+
 use core::alloc::{GlobalAlloc, Layout};
 
+#[cfg(any(feature = "allocator_api", feature = "allocator-api2"))]
+mod allocator;
+#[cfg(feature = "canaries")]
+mod canary;
+#[cfg(feature = "std")]
+mod defer;
+#[cfg(all(feature = "dont-dump", any(target_os = "linux", target_os = "android")))]
+mod dontdump;
+#[cfg(all(feature = "guard-pages", unix))]
+mod guarded;
+#[cfg(all(
+    feature = "secret-heap",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "haiku"
+    )
+))]
+mod heap;
+#[cfg(all(feature = "lock-pages", unix))]
+mod locked;
+#[cfg(all(
+    any(
+        feature = "guard-pages",
+        feature = "lock-pages",
+        feature = "dont-dump",
+        feature = "secret-heap"
+    ),
+    unix
+))]
+#[cfg_attr(
+    not(any(feature = "lock-pages", feature = "dont-dump")),
+    allow(dead_code) // `GuardedAlloc` and `SecretHeap` only need the page size
+)]
+mod pages;
+mod routing;
+#[cfg(feature = "std")]
+mod scope;
+#[cfg(feature = "selective")]
+mod secrets;
+#[cfg(any(
+    feature = "std",
+    feature = "selective",
+    all(
+        any(
+            feature = "guard-pages",
+            feature = "lock-pages",
+            feature = "dont-dump",
+            feature = "secret-heap"
+        ),
+        unix
+    )
+))]
+mod spin;
+#[cfg(feature = "stats")]
+mod stats;
+#[cfg(feature = "std")]
+mod system;
+#[cfg(any(
+    feature = "selective",
+    all(
+        any(
+            feature = "guard-pages",
+            feature = "lock-pages",
+            feature = "dont-dump",
+            feature = "secret-heap"
+        ),
+        unix
+    )
+))]
+#[cfg_attr(
+    not(any(feature = "selective", feature = "lock-pages", feature = "dont-dump")),
+    allow(dead_code) // Only the page table and the secrets table probe it
+)]
+mod table;
+mod wipe;
+
+#[cfg(feature = "canaries")]
+pub use canary::CanaryHook;
+#[cfg(feature = "std")]
+pub use defer::DeferQueue;
+#[cfg(all(feature = "dont-dump", any(target_os = "linux", target_os = "android")))]
+pub use dontdump::{DedicatedPages, DontDumpAlloc};
+#[cfg(all(feature = "guard-pages", unix))]
+pub use guarded::GuardedAlloc;
+#[cfg(all(
+    feature = "secret-heap",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "haiku"
+    )
+))]
+pub use heap::SecretHeap;
+#[cfg(all(feature = "lock-pages", unix))]
+pub use locked::LockedAlloc;
+pub use routing::RoutingAlloc;
+#[cfg(all(feature = "std", feature = "selective"))]
+pub use scope::secure_scope;
+#[cfg(feature = "std")]
+pub use scope::without_wiping;
+#[cfg(feature = "selective")]
+pub use secrets::mark_secret;
+#[cfg(feature = "stats")]
+pub use stats::Stats;
+#[cfg(feature = "std")]
+pub use system::ZeroizingSystem;
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+pub use wipe::ExplicitBzero;
+#[cfg(target_vendor = "apple")]
+pub use wipe::MemsetS;
+#[cfg(target_arch = "x86_64")]
+pub use wipe::NonTemporal;
+pub use wipe::{DefaultWiper, FnPtrMemset, VolatileLoop, Wiper};
+
 /// Allocator wrapper that zeros on free
-pub struct ZeroAlloc<Alloc: GlobalAlloc>(pub Alloc);
+///
+/// Blocks are wiped by `W`, see [`Wiper`] for the strategies available. Besides `GlobalAlloc`, this implements
+/// `Allocator` behind the nightly-only feature "allocator_api" (or "allocator-api2" on stable), for zeroizing individual
+/// collections.
+///
+/// `ZeroAlloc(inner)` still constructs one as it did when this was a tuple struct, and is equivalent to
+/// [`ZeroAlloc::new`], but can't be matched as a pattern. The wrapped allocator is reached through
+/// [`inner`](Self::inner) rather than `.0`.
+pub struct ZeroAlloc<Alloc, W = DefaultWiper> {
+    inner: Alloc,
+    wiper: W,
+    pattern: u8,
+    wipe_on_alloc: bool,
+    max_size: usize,
+    resize_in_place: Option<(ResizeFn<Alloc>, ResizeFn<Alloc>)>,
+    usable_size: Option<UsableSizeFn<Alloc>>,
+    #[cfg(feature = "selective")]
+    selective: bool,
+    #[cfg(feature = "canaries")]
+    canaries: Option<CanaryHook>,
+    #[cfg(feature = "std")]
+    deferred: Option<(&'static dyn defer::Queue, usize)>,
+    #[cfg(feature = "stats")]
+    stats: stats::Counters,
+}
 
-// Reference implementation. Performance-wise, this is the same as using the `zeroize` crate,
-// because it uses the same logic:
-//
-// ```rust
-// unsafe fn zero(ptr: *mut u8, size: usize) {
-//     use zeroize::Zeroize;
-//     core::slice::from_raw_parts_mut(ptr, size).zeroize();
-// }
-// ```
+/// Constructor keeping `ZeroAlloc(inner)` working now that [`ZeroAlloc`] has private fields.
 //
-// SAFETY: exactly one callsite (below), always passes the correct size
-#[cfg(feature = "reference_impl")]
-#[inline]
-unsafe fn zero(ptr: *mut u8, len: usize) {
-    for i in 0..len {
-        core::ptr::write_volatile(ptr.add(i), 0);
-    }
-    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+// Braced structs only live in the type namespace, so this can share the name of the struct without clashing with it.
+#[doc(hidden)]
+#[allow(non_snake_case)]
+pub const fn ZeroAlloc<Alloc>(inner: Alloc) -> ZeroAlloc<Alloc> {
+    ZeroAlloc::new(inner)
 }
 
-#[cfg(not(feature = "reference_impl"))]
-unsafe fn clear_bytes(ptr: *mut u8, len: usize) {
-    // We expect this to optimize into a `memset` for performance. Due to this function only being used via `read_volatile`,
-    // the compiler doesn't know that the slice this function will be wiping is about to be destroyed anyway.
-    //
-    // SAFETY: The caller must only pass a valid allocated object.
-    ptr.write_bytes(0x0, len);
+type ResizeFn<Alloc> = unsafe fn(&Alloc, *mut u8, Layout, usize) -> bool;
+type UsableSizeFn<Alloc> = unsafe fn(&Alloc, *mut u8, Layout) -> usize;
+
+impl<Alloc> ZeroAlloc<Alloc> {
+    /// Wraps `inner`, wiping every block with the [`DefaultWiper`] before it is handed back to it.
+    pub const fn new(inner: Alloc) -> Self {
+        Self::with_wiper(inner, DefaultWiper {})
+    }
 }
 
-// This is meant to avoid compiler optimizations while still retaining performance.
-//
-// By storing a function to a performant `memset(0, dest)` call, we can performantly zero out bytes
-// without the compiler realizing the values being cleared aren't going to be read from again since it does
-// not know either the source of the bytes or the source of our clearing function.
-//
-// - By loading this function pointer volatilely, we ensure the compiler does not optimize thinking about the
-// source of the function pointer.
-// - `#[used]` presents an extra optimization barrier since it forces the compiler to keep it around (won't take part in codegen optimization)
-// until it reaches the linker. Even if the linker removes it though, its still fine because that can't optimize code that depends on it.
-#[cfg(not(feature = "reference_impl"))]
-#[used]
-static WIPER: unsafe fn(*mut u8, usize) = clear_bytes;
-
-// SAFETY: exactly one callsite (below), always passes the correct size
-#[cfg(not(feature = "reference_impl"))]
-#[inline]
-unsafe fn zero(ptr: *mut u8, len: usize) {
-    // The compiler may not predict anything about the clearing function we load due to the `read_volatile`, so
-    // it must always load it from the static's address instead of directly calling the `clear_bytes` function (which
-    // might allow optimizing away clearing).
+impl<Alloc, W> ZeroAlloc<Alloc, W> {
+    /// Wraps `inner`, wiping every block with `wiper` before it is handed back to it.
+    pub const fn with_wiper(inner: Alloc, wiper: W) -> Self {
+        Self {
+            inner,
+            wiper,
+            pattern: 0,
+            wipe_on_alloc: false,
+            max_size: usize::MAX,
+            resize_in_place: None,
+            usable_size: None,
+            #[cfg(feature = "selective")]
+            selective: false,
+            #[cfg(feature = "canaries")]
+            canaries: None,
+            #[cfg(feature = "std")]
+            deferred: None,
+            #[cfg(feature = "stats")]
+            stats: stats::Counters::new(),
+        }
+    }
+
+    /// Fills freed memory with `pattern` instead of zeros.
+    ///
+    /// Poison bytes like `0xDE` make stale reads after a use-after-free stand out, where zeros could pass for valid data.
+    pub const fn with_pattern(mut self, pattern: u8) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    /// Also clears blocks when they are allocated, giving every `alloc` the semantics of `alloc_zeroed`.
+    ///
+    /// This scrubs memory the inner allocator may have recycled without it passing through a [`ZeroAlloc`], such as blocks
+    /// freed before it was installed or released by foreign code calling `free` directly.
+    pub const fn with_wipe_on_alloc(mut self) -> Self {
+        self.wipe_on_alloc = true;
+        self
+    }
+
+    /// Only wipes allocations of at most `max_size` bytes, skipping larger ones.
+    ///
+    /// Keys and tokens live in small allocations, while wiping large non-secret buffers (such as media) can be a measurable cost.
+    pub const fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Lets `realloc` resize blocks in place through [`ResizeInPlace`] instead of always moving them.
+    ///
+    /// When shrinking, the truncated tail is wiped before the inner allocator takes it back, once it has confirmed the
+    /// shrink will succeed.
+    pub const fn with_resize_in_place(mut self) -> Self
+    where
+        Alloc: ResizeInPlace,
+    {
+        self.resize_in_place = Some((Alloc::can_resize_in_place, Alloc::resize_in_place));
+        self
+    }
+
+    /// Wipes each block up to the usable size reported through [`UsableSize`], rather than only its `Layout::size()`.
+    pub const fn with_usable_size(mut self) -> Self
+    where
+        Alloc: UsableSize,
+    {
+        self.usable_size = Some(Alloc::usable_size);
+        self
+    }
+
+    /// Returns a reference to the wrapped allocator.
+    pub const fn inner(&self) -> &Alloc {
+        &self.inner
+    }
+
+    /// Returns how much work this allocator has done so far, e.g. to check it is installed and wiping.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> Stats {
+        self.stats.snapshot()
+    }
+}
+
+impl<Alloc, W> ZeroAlloc<Alloc, W> {
+    // Returns whether blocks allocated through `GlobalAlloc` are surrounded by redzones.
+    #[inline]
+    fn redzones(&self) -> bool {
+        #[cfg(feature = "canaries")]
+        return self.canaries.is_some();
+        #[cfg(not(feature = "canaries"))]
+        false
+    }
+}
+
+impl<Alloc: GlobalAlloc, W: Wiper> ZeroAlloc<Alloc, W> {
+    // Allocates the block from the inner allocator, padded with redzones when canaries are enabled.
+    #[inline]
+    unsafe fn inner_alloc(&self, layout: Layout, zeroed: bool) -> *mut u8 {
+        #[cfg(feature = "canaries")]
+        if self.canaries.is_some() {
+            return self.alloc_with_redzones(layout, zeroed);
+        }
+        if zeroed {
+            self.inner.alloc_zeroed(layout)
+        } else {
+            self.inner.alloc(layout)
+        }
+    }
+
+    // Hands the block back to the inner allocator, checking its redzones first when canaries are enabled.
+    #[inline]
+    unsafe fn inner_dealloc(&self, ptr: *mut u8, layout: Layout) {
+        #[cfg(feature = "canaries")]
+        if let Some(hook) = self.canaries {
+            return self.dealloc_with_redzones(ptr, layout, hook);
+        }
+        self.inner.dealloc(ptr, layout);
+    }
+}
+
+impl<Alloc, W: Wiper> ZeroAlloc<Alloc, W> {
+    // Wipes the bytes of the block at `ptr` starting at offset `from` up to its (usable) end, unless the block is above the
+    // size threshold. Blocks between `redzones` don't extend to the end of what the inner allocator handed out.
     //
-    // SAFETY: This static is always initialized to the correct value.
-    let wipe = unsafe { core::ptr::addr_of!(WIPER).read_volatile() };
-    wipe(ptr, len);
+    // SAFETY: callers must pass a live allocation and its layout, and `from` must not exceed its size
+    #[inline]
+    unsafe fn zero(&self, ptr: *mut u8, layout: Layout, from: usize, redzones: bool) {
+        // Zero-sized blocks of `Allocator` are dangling pointers, which the inner allocator can't report a usable size for
+        if layout.size() == 0 {
+            return;
+        }
+        #[cfg(feature = "std")]
+        if scope::wiping_suppressed() {
+            return;
+        }
+        #[cfg(feature = "selective")]
+        if !self.wipes(ptr) {
+            return;
+        }
+        if layout.size() <= self.max_size {
+            let end = match self.usable_size {
+                Some(usable_size) if !redzones => {
+                    core::cmp::max(usable_size(&self.inner, ptr, layout), layout.size())
+                }
+                _ => layout.size(),
+            };
+            self.wiper.wipe(ptr.add(from), end - from, self.pattern);
+            #[cfg(feature = "stats")]
+            self.stats.wiped(end - from);
+        }
+    }
+}
+
+/// Allocators that can resize a block without moving it.
+///
+/// [`GlobalAlloc::realloc`] is free to move a block and release the old one itself, which would hand
+/// un-wiped bytes back to the allocator. Implementing this lets [`ZeroAlloc`] keep in-place resizing
+/// while every block it moves is still wiped.
+///
+/// With feature "std", `std::alloc::System` implements this on Linux and Android, resizing blocks within the slack
+/// reported by `malloc_usable_size`. Growing past it still moves the block, as the C library's `realloc` can't be
+/// trusted not to.
+///
+/// # Safety
+///
+/// If `resize_in_place` returns `true`, the block must be valid for, and later be deallocated with, a
+/// layout of `new_size` bytes and the original alignment. If it returns `false`, the block must be left untouched.
+/// Once `can_resize_in_place` has returned `true`, a following `resize_in_place` call with the same arguments must
+/// return `true`.
+pub unsafe trait ResizeInPlace {
+    /// Returns whether the block at `ptr` can be resized to `new_size` bytes without moving it.
+    ///
+    /// [`ZeroAlloc`] asks before shrinking a block: the truncated tail has to be wiped before the allocator takes it back,
+    /// but must be left intact if the block ends up being moved, or not reallocated at all.
+    ///
+    /// # Safety
+    ///
+    /// Same as [`resize_in_place`](Self::resize_in_place).
+    unsafe fn can_resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool;
+
+    /// Attempts to resize the block at `ptr` to `new_size` bytes without moving it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be currently allocated by `self` with `layout`, and `new_size` must be non-zero and
+    /// must not overflow `isize` when rounded up to `layout.align()`.
+    unsafe fn resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool;
+}
+
+/// Allocators that can report how many bytes a block really spans.
+///
+/// Many allocators (glibc malloc, jemalloc, mimalloc) hand out blocks larger than `Layout::size()`, and code sizing its writes with
+/// e.g. `malloc_usable_size` can leave secrets in that slack. Implementing this lets [`ZeroAlloc`] wipe the full usable size.
+///
+/// With feature "std", `std::alloc::System` implements this on Linux and Android through `malloc_usable_size`. Allocators
+/// from other crates need a newtype around them to implement it.
+///
+/// # Safety
+///
+/// The returned size must not exceed the bytes of the block that may be written to.
+pub unsafe trait UsableSize {
+    /// Returns the usable size of the block at `ptr`, which is at least `layout.size()`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be currently allocated by `self` with `layout`.
+    unsafe fn usable_size(&self, ptr: *mut u8, layout: Layout) -> usize;
 }
 
 // SAFETY: wrapper for system allocator, zeroizes on free but otherwise re-uses system logic
-unsafe impl<T> GlobalAlloc for ZeroAlloc<T>
+unsafe impl<T, W> GlobalAlloc for ZeroAlloc<T, W>
 where
     T: GlobalAlloc,
+    W: Wiper,
 {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        self.0.alloc(layout)
+        let ptr = self.inner_alloc(layout, self.wipe_on_alloc);
+        #[cfg(feature = "selective")]
+        self.track(ptr);
+        #[cfg(feature = "stats")]
+        self.stats.allocated(ptr);
+        ptr
     }
 
     #[inline]
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        zero(ptr, layout.size());
-        self.0.dealloc(ptr, layout);
+        #[cfg(feature = "std")]
+        if self.defer(ptr, layout) {
+            return;
+        }
+        self.zero(ptr, layout, 0, self.redzones());
+        #[cfg(feature = "selective")]
+        self.untrack(ptr);
+        self.inner_dealloc(ptr, layout);
+        #[cfg(feature = "stats")]
+        self.stats.deallocated();
     }
 
     #[inline]
     unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
-        self.0.alloc_zeroed(layout)
+        let ptr = self.inner_alloc(layout, true);
+        #[cfg(feature = "selective")]
+        self.track(ptr);
+        #[cfg(feature = "stats")]
+        self.stats.allocated(ptr);
+        ptr
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // The inner allocator's own `realloc` may free the old block without us seeing it, so it is never
+        // called. Blocks only stay in place when the inner allocator can promise not to move them.
+        // Resizing would have to move the trailing redzone, so blocks with canaries always move
+        if let Some((can_resize, resize)) = self.resize_in_place.filter(|_| !self.redzones()) {
+            let resized = if new_size >= layout.size() {
+                resize(&self.inner, ptr, layout, new_size)
+            } else if can_resize(&self.inner, ptr, layout, new_size) {
+                // The truncated tail goes back to the inner allocator, so it's wiped first. That waits until the shrink
+                // can no longer fail, as a block that is moved instead (or not reallocated at all) must keep its contents.
+                self.zero(ptr, layout, new_size, false);
+                resize(&self.inner, ptr, layout, new_size)
+            } else {
+                false
+            };
+            if resized {
+                if self.wipe_on_alloc && new_size > layout.size() {
+                    ptr.add(layout.size())
+                        .write_bytes(0, new_size - layout.size());
+                }
+                return ptr;
+            }
+        }
+
+        // SAFETY: the caller guarantees `new_size`, rounded up to `layout.align()`, does not overflow
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            core::ptr::copy_nonoverlapping(ptr, new_ptr, core::cmp::min(layout.size(), new_size));
+            #[cfg(feature = "selective")]
+            self.inherit(ptr, new_ptr);
+            self.dealloc(ptr, layout);
+            #[cfg(feature = "stats")]
+            self.stats.moved();
+        }
+        new_ptr
     }
 }
@@ -0,0 +1,88 @@
+use crate::pages::{page_size, PageAlloc, PageState, PageTable};
+use core::alloc::{GlobalAlloc, Layout};
+
+/// Inner allocator locking the pages backing each block into RAM with `mlock`, so secrets are never written to swap.
+///
+/// Pages are unlocked once the last block on them is freed. Wrapped in a [`ZeroAlloc`](crate::ZeroAlloc), that happens
+/// after the block was wiped:
+///
+/// ```
+/// use std::alloc::System;
+/// use zeroizing_alloc::{LockedAlloc, ZeroAlloc};
+///
+/// static SECRETS: ZeroAlloc<LockedAlloc<System>> = ZeroAlloc::new(LockedAlloc::new(System));
+/// ```
+///
+/// Whether a page is locked is process-wide state, so every `LockedAlloc` counts blocks in the same table: a page stays
+/// locked until the last block on it is freed, whichever allocator it came from.
+///
+/// Locked memory is limited by `RLIMIT_MEMLOCK`, often to a few MiB. Pages which can't be locked, or tracked once more than
+/// a few thousand are locked at once, are left unlocked and counted in [`errors`](Self::errors); allocations never fail
+/// because of it. Once a page couldn't be tracked, pages are no longer unlocked, as other blocks may still need them.
+/// This is why it's best kept to the allocators holding secrets rather than installed globally.
+pub struct LockedAlloc<Alloc>(PageAlloc<Alloc, Mlock>);
+
+// Locks pages with `mlock`
+struct Mlock;
+
+// The blocks on each locked page, across every `LockedAlloc`
+static LOCKED: PageTable = PageTable::new();
+
+impl PageState for Mlock {
+    fn table() -> &'static PageTable {
+        &LOCKED
+    }
+
+    unsafe fn apply(&self, run: *mut u8, len: usize, lock: bool) -> usize {
+        let size = len * page_size();
+        if lock {
+            if libc::mlock(run.cast(), size) != 0 {
+                return len;
+            }
+        } else {
+            // Unlocking pages which failed to lock is harmless
+            libc::munlock(run.cast(), size);
+        }
+        0
+    }
+}
+
+impl<Alloc> LockedAlloc<Alloc> {
+    /// Wraps `inner`, locking the pages of every block it hands out.
+    pub const fn new(inner: Alloc) -> Self {
+        Self(PageAlloc::new(inner, Mlock))
+    }
+
+    /// Returns a reference to the wrapped allocator.
+    pub const fn inner(&self) -> &Alloc {
+        &self.0.inner
+    }
+
+    /// Returns how many pages backing live blocks are tracked, across every `LockedAlloc` in the process.
+    ///
+    /// This includes pages which failed to lock, as they are still counted until their last block is freed. Once a page
+    /// couldn't be tracked, pages staying locked after their last block was freed are no longer counted.
+    pub fn locked_pages(&self) -> usize {
+        LOCKED.len()
+    }
+
+    /// Returns how many pages could not be locked so far, e.g. because `RLIMIT_MEMLOCK` was exceeded.
+    pub fn errors(&self) -> usize {
+        self.0.errors()
+    }
+}
+
+// SAFETY: forwards to `PageAlloc`
+unsafe impl<Alloc: GlobalAlloc> GlobalAlloc for LockedAlloc<Alloc> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+}
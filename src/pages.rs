@@ -0,0 +1,327 @@
+use crate::spin::SpinLock;
+use crate::table::{self, find, remove};
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+pub(crate) fn page_size() -> usize {
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+    match PAGE_SIZE.load(Ordering::Relaxed) {
+        0 => {
+            // SAFETY: `sysconf` has no preconditions
+            let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+            PAGE_SIZE.store(size, Ordering::Relaxed);
+            size
+        }
+        size => size,
+    }
+}
+
+/// Returns the address of the first page and the number of pages spanned by `len` bytes at `addr`.
+pub(crate) fn pages(addr: usize, len: usize) -> (usize, usize) {
+    let page = page_size();
+    let start = addr & !(page - 1);
+    (start, (addr + len - start).div_ceil(page))
+}
+
+/// Per-page state a [`PageAlloc`] keeps set up while pages back its live blocks, such as being locked into RAM.
+pub(crate) trait PageState {
+    /// Counts the blocks on each page set up this way. The state is process-wide, so this is shared by every allocator
+    /// setting it up.
+    fn table() -> &'static PageTable;
+
+    /// Sets up `len` pages from `run` if `set_up` is true, or tears them down otherwise. Returns how many pages failed.
+    ///
+    /// # Safety
+    ///
+    /// The pages must be mapped, and back live blocks.
+    unsafe fn apply(&self, run: *mut u8, len: usize, set_up: bool) -> usize;
+}
+
+/// Inner allocator wrapper setting up `S` on the pages backing each block, and tearing it down once the last block on a
+/// page is freed.
+///
+/// Pages which can't be set up, or tracked once the table is full, are counted in [`errors`](Self::errors) rather than
+/// failing the allocation.
+pub(crate) struct PageAlloc<Alloc, S> {
+    pub(crate) inner: Alloc,
+    pub(crate) state: S,
+    errors: AtomicUsize,
+}
+
+impl<Alloc, S> PageAlloc<Alloc, S> {
+    pub(crate) const fn new(inner: Alloc, state: S) -> Self {
+        Self {
+            inner,
+            state,
+            errors: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns how many pages could not be set up or tracked so far.
+    pub(crate) fn errors(&self) -> usize {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
+
+impl<Alloc, S: PageState> PageAlloc<Alloc, S> {
+    fn set_up(&self, ptr: *mut u8, size: usize) {
+        let (start, count) = pages(ptr.addr(), size);
+        let untracked = S::table().retain(start, count, |run, len, set_up| {
+            self.apply(ptr, run, len, set_up)
+        });
+        self.errors.fetch_add(untracked, Ordering::Relaxed);
+    }
+
+    fn tear_down(&self, ptr: *mut u8, size: usize) {
+        let (start, count) = pages(ptr.addr(), size);
+        S::table().release(start, count, |run, len, set_up| {
+            self.apply(ptr, run, len, set_up)
+        });
+    }
+
+    // Sets up or tears down `len` pages from `run`, which back live blocks, `ptr` among them.
+    fn apply(&self, ptr: *mut u8, run: usize, len: usize, set_up: bool) {
+        // SAFETY: the pages back live blocks
+        let failed = unsafe { self.state.apply(ptr.with_addr(run), len, set_up) };
+        self.errors.fetch_add(failed, Ordering::Relaxed);
+    }
+}
+
+// SAFETY: forwards to the inner allocator, only setting up page state around it
+unsafe impl<Alloc: GlobalAlloc, S: PageState> GlobalAlloc for PageAlloc<Alloc, S> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.set_up(ptr, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            self.set_up(ptr, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.tear_down(ptr, layout.size());
+        self.inner.dealloc(ptr, layout);
+    }
+}
+
+/// How many distinct pages a [`PageTable`] can count blocks on at once.
+pub(crate) const TABLE_CAPACITY: usize = 4096;
+
+/// Counts the live blocks on each page, so per-page state (like being locked) is only set up by the first block and torn
+/// down by the last, even when small blocks share pages.
+///
+/// This lives inside an allocator, so it can't allocate: it's a fixed-size open-addressing hash table behind a spin lock.
+/// The system calls setting up and tearing down pages are made outside of the lock, by the thread which brought a page's
+/// count to or from zero. That thread owns the page until its state matches its count again, so it's set up once more
+/// should a block be allocated on it while it's being torn down.
+pub(crate) struct PageTable {
+    slots: SpinLock<[(usize, Page); TABLE_CAPACITY]>,
+    len: AtomicUsize,
+    // Once a page couldn't be counted, a later block on it may be, and reach a count of zero while the first is still live
+    overflowed: AtomicBool,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Page {
+    // Live blocks on the page
+    blocks: usize,
+    // Whether the page is set up, or about to be by its owner
+    set_up: bool,
+    // The call bringing the page's state in line with `blocks`, if any, identified by the address of its stack frame
+    owner: usize,
+}
+
+const EMPTY: Page = Page {
+    blocks: 0,
+    set_up: false,
+    owner: 0,
+};
+
+impl PageTable {
+    pub(crate) const fn new() -> Self {
+        Self {
+            slots: SpinLock::new([(0, EMPTY); TABLE_CAPACITY]),
+            len: AtomicUsize::new(0),
+            overflowed: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns how many pages are tracked: those with live blocks, and those still being torn down.
+    pub(crate) fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Counts a new block on `count` pages from `start`, calling `apply(run, len, true)` to set up each run of pages which
+    /// had no blocks yet. Returns once all of them are set up, which another thread may be doing.
+    ///
+    /// Returns how many pages couldn't be counted because the table is full. These are left out of the runs, and from then
+    /// on pages are never torn down again: which pages still back live blocks is no longer known.
+    pub(crate) fn retain(
+        &self,
+        start: usize,
+        count: usize,
+        apply: impl FnMut(usize, usize, bool),
+    ) -> usize {
+        let mut untracked = 0;
+        let contested = self.update(start, count, apply, |slots, page| {
+            match find(slots, home, page) {
+                Ok(i) => {
+                    slots[i].1.blocks += 1;
+                    Some(i)
+                }
+                Err(Some(i)) => {
+                    slots[i] = (page, Page { blocks: 1, ..EMPTY });
+                    self.len.fetch_add(1, Ordering::Relaxed);
+                    Some(i)
+                }
+                Err(None) => {
+                    self.overflowed.store(true, Ordering::Relaxed);
+                    untracked += 1;
+                    None
+                }
+            }
+        });
+
+        // A page owned by another thread may have been being torn down, and is only set up again once that thread notices
+        if contested {
+            let page = page_size();
+            while self.slots.with(|slots| {
+                (0..count).any(|i| {
+                    find(slots, home, start + i * page).is_ok_and(|slot| slots[slot].1.owner != 0)
+                })
+            }) {
+                core::hint::spin_loop();
+            }
+        }
+        untracked
+    }
+
+    /// Uncounts a block on `count` pages from `start`, calling `apply(run, len, false)` to tear down each run of pages
+    /// which have no blocks left, unless the table ever overflowed.
+    pub(crate) fn release(
+        &self,
+        start: usize,
+        count: usize,
+        apply: impl FnMut(usize, usize, bool),
+    ) {
+        self.update(start, count, apply, |slots, page| {
+            match find(slots, home, page) {
+                // Once the table overflowed, blocks which weren't counted may find their page without any
+                Ok(i) if slots[i].1.blocks != 0 => {
+                    slots[i].1.blocks -= 1;
+                    Some(i)
+                }
+                _ => None,
+            }
+        });
+    }
+
+    // Recounts every page under the lock through `step`, which returns the page's slot if it was counted. Pages whose state
+    // no longer matches their count are then set up or torn down in runs through `apply`, outside of the lock.
+    //
+    // Returns whether any of the pages was owned by another thread.
+    fn update(
+        &self,
+        start: usize,
+        count: usize,
+        mut apply: impl FnMut(usize, usize, bool),
+        mut step: impl FnMut(&mut [(usize, Page)], usize) -> Option<usize>,
+    ) -> bool {
+        let frame = 0u8;
+        let token = core::ptr::addr_of!(frame).addr();
+        let page = page_size();
+
+        let (mut claimed, mut contested) = (false, false);
+        self.slots.with(|slots| {
+            for i in 0..count {
+                let Some(slot) = step(slots, start + i * page) else {
+                    continue;
+                };
+                if slots[slot].1.owner != 0 {
+                    contested = true;
+                    continue;
+                }
+                self.settle(slots, slot, token);
+                // The slot may have been emptied, or refilled by shifting another page back
+                claimed |= slots[slot].1.owner == token;
+            }
+        });
+        if claimed {
+            while let Some((run, len, set_up)) = self
+                .slots
+                .with(|slots| self.next_run(slots, start, count, token))
+            {
+                apply(run, len, set_up);
+            }
+        }
+        contested
+    }
+
+    // Finds the first run of pages owned by `token` whose state doesn't match their count, marking them as set up (or torn
+    // down) ahead of the system call doing it. Owned pages which are in line are let go of along the way.
+    fn next_run(
+        &self,
+        slots: &mut [(usize, Page)],
+        start: usize,
+        count: usize,
+        token: usize,
+    ) -> Option<(usize, usize, bool)> {
+        let page = page_size();
+        let mut run: Option<(usize, usize, bool)> = None;
+        for i in 0..count {
+            let addr = start + i * page;
+            let slot = match find(slots, home, addr) {
+                Ok(slot) if slots[slot].1.owner == token => slot,
+                _ if run.is_some() => break,
+                _ => continue,
+            };
+            let wanted = self.wanted(&slots[slot].1);
+            let entry = &mut slots[slot].1;
+            match &mut run {
+                None if entry.set_up == wanted => self.settle(slots, slot, token),
+                None => {
+                    entry.set_up = wanted;
+                    run = Some((addr, 1, wanted));
+                }
+                Some((_, len, set_up)) if entry.set_up != wanted && *set_up == wanted => {
+                    entry.set_up = wanted;
+                    *len += 1;
+                }
+                Some(_) => break,
+            }
+        }
+        run
+    }
+
+    // Hands the page in `slot` to `owner` if its state doesn't match its count. Otherwise the page is left without an owner,
+    // and dropped from the table once it has no blocks left.
+    fn settle(&self, slots: &mut [(usize, Page)], slot: usize, owner: usize) {
+        let entry = &mut slots[slot].1;
+        if entry.set_up != self.wanted(entry) {
+            entry.owner = owner;
+            return;
+        }
+        entry.owner = 0;
+        if entry.blocks == 0 {
+            remove(slots, home, slot);
+            self.len.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    // Returns whether the page should be set up: while it has blocks, and for good once the table overflowed.
+    fn wanted(&self, page: &Page) -> bool {
+        page.blocks != 0 || (page.set_up && self.overflowed.load(Ordering::Relaxed))
+    }
+}
+
+fn home(page: usize) -> usize {
+    table::hash(page / page_size(), TABLE_CAPACITY)
+}
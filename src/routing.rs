@@ -0,0 +1,114 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr;
+
+/// Allocator dispatching each block to one of two inner allocators by its [`Layout`].
+///
+/// Small allocations typically hold keys and tokens, while large ones hold bulk data. This sends blocks of at most
+/// [`max_small_size`](Self::with_max_small_size) bytes and [`max_small_align`](Self::with_max_small_align) alignment to
+/// `Small`, and everything else to `Large`, e.g. to only pay for a hardened, zeroizing heap where secrets live:
+///
+/// ```
+/// use std::alloc::System;
+/// use zeroizing_alloc::{RoutingAlloc, ZeroAlloc};
+///
+/// #[global_allocator]
+/// static ALLOC: RoutingAlloc<ZeroAlloc<System>, System> =
+///     RoutingAlloc::new(ZeroAlloc::new(System), System).with_max_small_size(256);
+/// ```
+///
+/// Blocks are always freed through the allocator they came from. `realloc` moves blocks between the two when their new
+/// size crosses the threshold.
+pub struct RoutingAlloc<Small, Large> {
+    small: Small,
+    large: Large,
+    max_small_size: usize,
+    max_small_align: usize,
+}
+
+impl<Small, Large> RoutingAlloc<Small, Large> {
+    /// Routes blocks of up to 1 KiB, aligned to at most 16 bytes, to `small`, and everything else to `large`.
+    pub const fn new(small: Small, large: Large) -> Self {
+        Self {
+            small,
+            large,
+            max_small_size: 1024,
+            max_small_align: 16,
+        }
+    }
+
+    /// Routes blocks of up to `max_small_size` bytes to the small allocator.
+    pub const fn with_max_small_size(mut self, max_small_size: usize) -> Self {
+        self.max_small_size = max_small_size;
+        self
+    }
+
+    /// Routes blocks aligned to at most `max_small_align` bytes to the small allocator.
+    pub const fn with_max_small_align(mut self, max_small_align: usize) -> Self {
+        self.max_small_align = max_small_align;
+        self
+    }
+
+    /// Returns a reference to the allocator serving small blocks.
+    pub const fn small(&self) -> &Small {
+        &self.small
+    }
+
+    /// Returns a reference to the allocator serving large blocks.
+    pub const fn large(&self) -> &Large {
+        &self.large
+    }
+
+    #[inline]
+    fn is_small(&self, layout: Layout) -> bool {
+        layout.size() <= self.max_small_size && layout.align() <= self.max_small_align
+    }
+}
+
+// SAFETY: every block is served by, and given back to, the same inner allocator
+unsafe impl<Small: GlobalAlloc, Large: GlobalAlloc> GlobalAlloc for RoutingAlloc<Small, Large> {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if self.is_small(layout) {
+            self.small.alloc(layout)
+        } else {
+            self.large.alloc(layout)
+        }
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if self.is_small(layout) {
+            self.small.dealloc(ptr, layout)
+        } else {
+            self.large.dealloc(ptr, layout)
+        }
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if self.is_small(layout) {
+            self.small.alloc_zeroed(layout)
+        } else {
+            self.large.alloc_zeroed(layout)
+        }
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: the caller guarantees `new_size`, rounded up to `layout.align()`, does not overflow
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        match (self.is_small(layout), self.is_small(new_layout)) {
+            (true, true) => self.small.realloc(ptr, layout, new_size),
+            (false, false) => self.large.realloc(ptr, layout, new_size),
+            // Crossing the threshold: the block has to move to the other allocator, which frees the old one as usual
+            _ => {
+                let new_ptr = self.alloc(new_layout);
+                if !new_ptr.is_null() {
+                    ptr::copy_nonoverlapping(ptr, new_ptr, core::cmp::min(layout.size(), new_size));
+                    self.dealloc(ptr, layout);
+                }
+                new_ptr
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use core::cell::Cell;
+use std::thread::LocalKey;
+
+std::thread_local! {
+    // `const` and destructor-free, so reading these from inside the allocator can't allocate
+    static SUPPRESSED: Cell<bool> = const { Cell::new(false) };
+    #[cfg(feature = "selective")]
+    static SECURE: Cell<bool> = const { Cell::new(false) };
+}
+
+// Sets `flag` while running `f`, restoring its previous value even if `f` panics.
+fn with_flag<R>(flag: &'static LocalKey<Cell<bool>>, f: impl FnOnce() -> R) -> R {
+    struct Restore(&'static LocalKey<Cell<bool>>, bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            self.0.set(self.1);
+        }
+    }
+
+    let _restore = Restore(flag, flag.replace(true));
+    f()
+}
+
+/// Runs `f` without wiping the blocks the current thread frees, to spare hot loops churning through non-secret data.
+///
+/// The flag is per thread, and only consulted when freeing: blocks freed on this thread inside `f` are not wiped, wherever
+/// they were allocated, while blocks allocated inside `f` but freed on another thread or after `f` returns are wiped as
+/// usual. Wiping resumes when `f` returns or panics, and nested calls restore the outer setting.
+///
+/// ```
+/// let lengths: usize = zeroizing_alloc::without_wiping(|| (0..1000).map(|i| i.to_string().len()).sum());
+/// ```
+pub fn without_wiping<R>(f: impl FnOnce() -> R) -> R {
+    with_flag(&SUPPRESSED, f)
+}
+
+/// Returns whether the current thread is inside [`without_wiping`]. Threads being torn down always wipe.
+#[inline]
+pub(crate) fn wiping_suppressed() -> bool {
+    SUPPRESSED.try_with(Cell::get).unwrap_or(false)
+}
+
+/// Runs `f`, marking every block the current thread allocates inside it as secret.
+///
+/// Allocators in [selective mode](crate::ZeroAlloc::with_selective_wiping) only wipe secret blocks, wherever and whenever
+/// they are freed. Allocators not in selective mode ignore this, as they wipe every block anyway.
+///
+/// ```
+/// let key = zeroizing_alloc::secure_scope(|| vec![0x42u8; 32]);
+/// ```
+#[cfg(feature = "selective")]
+pub fn secure_scope<R>(f: impl FnOnce() -> R) -> R {
+    with_flag(&SECURE, f)
+}
+
+/// Returns whether the current thread is inside [`secure_scope`].
+#[cfg(feature = "selective")]
+#[inline]
+pub(crate) fn in_secure_scope() -> bool {
+    SECURE.try_with(Cell::get).unwrap_or(false)
+}
@@ -0,0 +1,139 @@
+use crate::spin::SpinLock;
+use crate::table::{self, find, remove};
+use crate::ZeroAlloc;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// How many shards the table of secret blocks is split into, each behind a lock of its own.
+const SHARDS: usize = 16;
+/// How many secret blocks each shard can track at once.
+const SHARD_CAPACITY: usize = 256;
+
+// The addresses of live secret blocks, shared by every allocator so blocks can be freed from any thread. Blocks are spread
+// over shards by address, so frees on different threads rarely contend for the same lock.
+static SECRETS: [Shard; SHARDS] = [const { Shard::new() }; SHARDS];
+// Once a secret couldn't be recorded, there's no telling which blocks are secret anymore
+static OVERFLOWED: AtomicBool = AtomicBool::new(false);
+
+struct Shard {
+    slots: SpinLock<[(usize, ()); SHARD_CAPACITY]>,
+    // How many slots are taken, so allocators can skip the lock while none of the shard's blocks is marked
+    len: AtomicUsize,
+}
+
+impl Shard {
+    const fn new() -> Self {
+        Self {
+            slots: SpinLock::new([(0, ()); SHARD_CAPACITY]),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    // Returns the shard tracking the block at `addr`.
+    fn of(addr: usize) -> &'static Self {
+        &SECRETS[table::hash(key(addr), SHARDS)]
+    }
+}
+
+// Blocks are at least word-aligned, so the low bits carry no information
+fn key(addr: usize) -> usize {
+    addr >> 3
+}
+
+fn home(addr: usize) -> usize {
+    // The bits of the hash right below those picking the shard
+    table::hash(key(addr), SHARDS * SHARD_CAPACITY) % SHARD_CAPACITY
+}
+
+/// Marks the block at `ptr` as secret, so an allocator in [selective mode](ZeroAlloc::with_selective_wiping) wipes it
+/// when it's freed.
+///
+/// `ptr` should be a live block of a [`ZeroAlloc`] in selective mode, which forgets the mark when the block is freed. Other
+/// allocators wipe every block anyway, and never look at the marks: blocks of theirs keep their mark until a block at the
+/// same address is freed by a selective allocator. Should more than a few thousand secret blocks be marked at once,
+/// selective allocators fall back to wiping every block.
+///
+/// Marks are kept in a table split into 16 shards by address. While any block of a shard is marked, selective allocators
+/// freeing a block of that shard look it up under the shard's spin lock.
+pub fn mark_secret(ptr: *mut u8) {
+    if ptr.is_null() {
+        return;
+    }
+    let addr = ptr.addr();
+    let shard = Shard::of(addr);
+    let recorded = shard.slots.with(|slots| match find(slots, home, addr) {
+        Ok(_) => true,
+        Err(Some(i)) => {
+            slots[i] = (addr, ());
+            shard.len.fetch_add(1, Ordering::Relaxed);
+            true
+        }
+        Err(None) => false,
+    });
+    if !recorded {
+        OVERFLOWED.store(true, Ordering::Relaxed);
+    }
+}
+
+// Returns whether the block at `ptr` must be wiped in selective mode.
+fn is_secret(ptr: *mut u8) -> bool {
+    let addr = ptr.addr();
+    let shard = Shard::of(addr);
+    OVERFLOWED.load(Ordering::Relaxed)
+        || (shard.len.load(Ordering::Relaxed) != 0
+            && shard.slots.with(|slots| find(slots, home, addr).is_ok()))
+}
+
+impl<Alloc, W> ZeroAlloc<Alloc, W> {
+    /// Only wipes blocks allocated inside `secure_scope` (feature "std"), or marked through [`mark_secret`].
+    ///
+    /// This trades wiping everything for tracking secret blocks in a table, for services where secrets are the exception.
+    /// Blocks stay secret when `realloc` moves them, and are wiped whichever thread frees them.
+    pub const fn with_selective_wiping(mut self) -> Self {
+        self.selective = true;
+        self
+    }
+
+    // Returns whether the block at `ptr` is to be wiped when freed.
+    #[inline]
+    pub(crate) fn wipes(&self, ptr: *mut u8) -> bool {
+        !self.selective || is_secret(ptr)
+    }
+
+    // Marks a new block as secret when allocated in a secure scope.
+    #[inline]
+    pub(crate) fn track(&self, ptr: *mut u8) {
+        #[cfg(feature = "std")]
+        if self.selective && crate::scope::in_secure_scope() {
+            mark_secret(ptr);
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = ptr;
+    }
+
+    // Carries the secret mark over when a block moves from `old` to `new`.
+    #[inline]
+    pub(crate) fn inherit(&self, old: *mut u8, new: *mut u8) {
+        if self.selective && is_secret(old) {
+            mark_secret(new);
+        }
+    }
+
+    // Forgets a block about to be handed back to the inner allocator, which may reuse its address for a non-secret block.
+    #[inline]
+    pub(crate) fn untrack(&self, ptr: *mut u8) {
+        if !self.selective {
+            return;
+        }
+        let addr = ptr.addr();
+        let shard = Shard::of(addr);
+        if shard.len.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        shard.slots.with(|slots| {
+            if let Ok(i) = find(slots, home, addr) {
+                remove(slots, home, i);
+                shard.len.fetch_sub(1, Ordering::Relaxed);
+            }
+        });
+    }
+}
@@ -0,0 +1,71 @@
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+/// A snapshot of the work done by a [`ZeroAlloc`](crate::ZeroAlloc), returned by [`ZeroAlloc::stats`](crate::ZeroAlloc::stats).
+///
+/// Counters wrap around on overflow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Blocks handed out, including those made to move a block in `realloc`.
+    pub allocations: usize,
+    /// Blocks released, including those left behind when moving a block in `realloc`.
+    pub deallocations: usize,
+    /// Total number of bytes wiped.
+    pub bytes_wiped: usize,
+    /// Largest number of bytes wiped at once.
+    pub largest_wipe: usize,
+    /// `realloc` calls which had to move the block.
+    pub realloc_moves: usize,
+}
+
+pub(crate) struct Counters {
+    allocations: AtomicUsize,
+    deallocations: AtomicUsize,
+    bytes_wiped: AtomicUsize,
+    largest_wipe: AtomicUsize,
+    realloc_moves: AtomicUsize,
+}
+
+impl Counters {
+    pub(crate) const fn new() -> Self {
+        Self {
+            allocations: AtomicUsize::new(0),
+            deallocations: AtomicUsize::new(0),
+            bytes_wiped: AtomicUsize::new(0),
+            largest_wipe: AtomicUsize::new(0),
+            realloc_moves: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> Stats {
+        Stats {
+            allocations: self.allocations.load(Relaxed),
+            deallocations: self.deallocations.load(Relaxed),
+            bytes_wiped: self.bytes_wiped.load(Relaxed),
+            largest_wipe: self.largest_wipe.load(Relaxed),
+            realloc_moves: self.realloc_moves.load(Relaxed),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn allocated(&self, ptr: *mut u8) {
+        if !ptr.is_null() {
+            self.allocations.fetch_add(1, Relaxed);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn deallocated(&self) {
+        self.deallocations.fetch_add(1, Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn wiped(&self, len: usize) {
+        self.bytes_wiped.fetch_add(len, Relaxed);
+        self.largest_wipe.fetch_max(len, Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn moved(&self) {
+        self.realloc_moves.fetch_add(1, Relaxed);
+    }
+}
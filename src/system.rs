@@ -0,0 +1,105 @@
+use crate::{ResizeInPlace, UsableSize, ZeroAlloc};
+use core::alloc::{GlobalAlloc, Layout};
+use core::ops::Deref;
+use std::alloc::System;
+
+/// [`ZeroAlloc`] over the [`System`] allocator, ready to be installed as the global allocator.
+///
+/// ```
+/// #[global_allocator]
+/// static ALLOC: zeroizing_alloc::ZeroizingSystem = zeroizing_alloc::ZeroizingSystem::new();
+/// ```
+///
+/// Derefs to the wrapped [`ZeroAlloc`], to reach e.g. its statistics.
+pub struct ZeroizingSystem(ZeroAlloc<System>);
+
+impl ZeroizingSystem {
+    /// Wraps [`System`] in a [`ZeroAlloc`] with its default configuration.
+    pub const fn new() -> Self {
+        Self(ZeroAlloc::new(System))
+    }
+}
+
+impl Default for ZeroizingSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for ZeroizingSystem {
+    type Target = ZeroAlloc<System>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// SAFETY: forwards to `ZeroAlloc<System>`
+unsafe impl GlobalAlloc for ZeroizingSystem {
+    #[inline]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc(layout)
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.dealloc(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc_zeroed(layout)
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.0.realloc(ptr, layout, new_size)
+    }
+}
+
+// SAFETY: `System` hands out blocks from the C library's `malloc` family on these targets, for which
+// `malloc_usable_size` reports how many bytes may be written
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe impl UsableSize for System {
+    #[inline]
+    unsafe fn usable_size(&self, ptr: *mut u8, _layout: Layout) -> usize {
+        extern "C" {
+            fn malloc_usable_size(ptr: *mut core::ffi::c_void) -> usize;
+        }
+
+        malloc_usable_size(ptr.cast())
+    }
+}
+
+// SAFETY: `System` releases blocks with `free` whatever their layout, so a block stays valid for, and may be deallocated
+// with, any size up to its usable size
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe impl ResizeInPlace for System {
+    #[inline]
+    unsafe fn can_resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        new_size <= self.usable_size(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        self.can_resize_in_place(ptr, layout, new_size)
+    }
+}
+
+/// Installs a [`ZeroizingSystem`] as the global allocator.
+///
+/// The static is named `ZEROIZING_ALLOC` unless a name is given:
+///
+/// ```
+/// zeroizing_alloc::zeroizing_global_allocator!(ALLOC);
+/// ```
+#[macro_export]
+macro_rules! zeroizing_global_allocator {
+    () => {
+        $crate::zeroizing_global_allocator!(ZEROIZING_ALLOC);
+    };
+    ($name:ident) => {
+        #[global_allocator]
+        static $name: $crate::ZeroizingSystem = $crate::ZeroizingSystem::new();
+    };
+}
@@ -0,0 +1,58 @@
+//! Open addressing over a fixed array of `(key, value)` slots, for the tables allocators keep without allocating.
+//!
+//! Key 0 marks empty slots, and each table picks where a key's probe sequence starts through its `home` function.
+
+// Returns the slot holding `key`, or else the empty slot it would go into, if any.
+pub(crate) fn find<V>(
+    slots: &[(usize, V)],
+    home: fn(usize) -> usize,
+    key: usize,
+) -> Result<usize, Option<usize>> {
+    let mut i = home(key);
+    for _ in 0..slots.len() {
+        match slots[i].0 {
+            0 => return Err(Some(i)),
+            k if k == key => return Ok(i),
+            _ => i = (i + 1) % slots.len(),
+        }
+    }
+    Err(None)
+}
+
+// Empties slot `i`, shifting later entries of its probe sequence back so lookups don't stop early at the gap.
+pub(crate) fn remove<V: Copy + Default>(
+    slots: &mut [(usize, V)],
+    home: fn(usize) -> usize,
+    mut i: usize,
+) {
+    let mut j = i;
+    loop {
+        slots[i] = (0, V::default());
+        loop {
+            j = (j + 1) % slots.len();
+            if slots[j].0 == 0 {
+                return;
+            }
+            // Entry `j` may fill the gap unless its home lies cyclically within `(i, j]`
+            let k = home(slots[j].0);
+            let stays = if i <= j {
+                i < k && k <= j
+            } else {
+                i < k || k <= j
+            };
+            if !stays {
+                break;
+            }
+        }
+        slots[i] = slots[j];
+        i = j;
+    }
+}
+
+// Fibonacci hashing: multiplying by 2^BITS / φ mixes every bit of `key` into the top bits of the product, which pick one
+// of `capacity` slots. Keys which only differ in their low bits, like neighbouring pages, land far apart.
+pub(crate) fn hash(key: usize, capacity: usize) -> usize {
+    debug_assert!(capacity.is_power_of_two() && capacity > 1);
+    const K: usize = (0x9E37_79B9_7F4A_7C15_u64 >> (64 - usize::BITS)) as usize;
+    key.wrapping_mul(K) >> (usize::BITS - capacity.trailing_zeros())
+}
@@ -0,0 +1,252 @@
+/// A strategy for wiping memory before it is handed back to the inner allocator.
+///
+/// Each [`ZeroAlloc`](crate::ZeroAlloc) picks its wiper through a type parameter, so different strategies can be
+/// compared side by side in one binary.
+pub trait Wiper {
+    /// Overwrites `len` bytes starting at `ptr` with `pattern`, in a way the compiler can't optimize out.
+    ///
+    /// `pattern` is zero unless a poison byte was picked through [`ZeroAlloc::with_pattern`](crate::ZeroAlloc::with_pattern).
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes of `len` bytes.
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize, pattern: u8);
+}
+
+/// The wiper used by [`ZeroAlloc::new`](crate::ZeroAlloc::new).
+#[cfg(all(
+    not(feature = "reference_impl"),
+    not(all(feature = "libc-wipe", target_os = "linux"))
+))]
+pub type DefaultWiper = FnPtrMemset;
+
+/// The wiper used by [`ZeroAlloc::new`](crate::ZeroAlloc::new).
+#[cfg(all(
+    not(feature = "reference_impl"),
+    feature = "libc-wipe",
+    target_os = "linux"
+))]
+pub type DefaultWiper = ExplicitBzero;
+
+/// The wiper used by [`ZeroAlloc::new`](crate::ZeroAlloc::new).
+#[cfg(feature = "reference_impl")]
+pub type DefaultWiper = VolatileLoop;
+
+/// Reference implementation, writing one byte at a time with volatile stores.
+///
+/// Performance-wise, this is the same as using the `zeroize` crate, because it uses the same logic:
+///
+/// ```rust,ignore
+/// unsafe fn zero(ptr: *mut u8, size: usize) {
+///     use zeroize::Zeroize;
+///     core::slice::from_raw_parts_mut(ptr, size).zeroize();
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VolatileLoop;
+
+impl Wiper for VolatileLoop {
+    #[inline]
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize, pattern: u8) {
+        for i in 0..len {
+            core::ptr::write_volatile(ptr.add(i), pattern);
+        }
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// A `memset` hidden behind a volatile function pointer load, which keeps the compiler from eliding it.
+///
+/// On x86_64 Linux, `cargo test` checks that an optimized `dealloc` still calls it (in `tests/codegen.rs`, which needs
+/// `objdump`).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FnPtrMemset;
+
+unsafe fn clear_bytes(ptr: *mut u8, len: usize, pattern: u8) {
+    // We expect this to optimize into a `memset` for performance. Due to this function only being used via `read_volatile`,
+    // the compiler doesn't know that the slice this function will be wiping is about to be destroyed anyway.
+    //
+    // SAFETY: The caller must only pass a valid allocated object.
+    ptr.write_bytes(pattern, len);
+}
+
+// This is meant to avoid compiler optimizations while still retaining performance.
+//
+// By storing a function to a performant `memset(dest, pattern, len)` call, we can performantly overwrite bytes
+// without the compiler realizing the values being cleared aren't going to be read from again since it does
+// not know either the source of the bytes or the source of our clearing function.
+//
+// - By loading this function pointer volatilely, we ensure the compiler does not optimize thinking about the
+// source of the function pointer.
+// - `#[used]` presents an extra optimization barrier since it forces the compiler to keep it around (won't take part in codegen optimization)
+// until it reaches the linker. Even if the linker removes it though, its still fine because that can't optimize code that depends on it.
+#[used]
+static WIPER: unsafe fn(*mut u8, usize, u8) = clear_bytes;
+
+impl Wiper for FnPtrMemset {
+    #[inline]
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize, pattern: u8) {
+        // The compiler may not predict anything about the clearing function we load due to the `read_volatile`, so
+        // it must always load it from the static's address instead of directly calling the `clear_bytes` function (which
+        // might allow optimizing away clearing).
+        //
+        // SAFETY: This static is always initialized to the correct value.
+        let wipe = unsafe { core::ptr::addr_of!(WIPER).read_volatile() };
+        wipe(ptr, len, pattern);
+    }
+}
+
+/// The C library's `explicit_bzero`, which is documented to never be optimized out.
+///
+/// Requires glibc 2.25+ or musl 1.1.20+ on Linux. This is the [`DefaultWiper`] on Linux with feature "libc-wipe".
+///
+/// `explicit_bzero` can only write zeros, so other patterns go through [`FnPtrMemset`] instead.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExplicitBzero;
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+impl Wiper for ExplicitBzero {
+    #[inline]
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize, pattern: u8) {
+        extern "C" {
+            fn explicit_bzero(s: *mut core::ffi::c_void, n: usize);
+        }
+
+        if pattern != 0 {
+            return FnPtrMemset.wipe(ptr, len, pattern);
+        }
+        explicit_bzero(ptr.cast(), len);
+    }
+}
+
+/// The C library's `memset_s` (C11 Annex K), which is documented to never be optimized out.
+#[cfg(target_vendor = "apple")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemsetS;
+
+#[cfg(target_vendor = "apple")]
+impl Wiper for MemsetS {
+    #[inline]
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize, pattern: u8) {
+        extern "C" {
+            fn memset_s(
+                s: *mut core::ffi::c_void,
+                smax: usize,
+                c: core::ffi::c_int,
+                n: usize,
+            ) -> core::ffi::c_int;
+        }
+
+        // This can only fail for null pointers or sizes above `RSIZE_MAX`, neither of which a live allocation has.
+        memset_s(ptr.cast(), len, pattern.into(), len);
+    }
+}
+
+/// Non-temporal (streaming) stores, which write large blocks without pulling them into the cache.
+///
+/// Wiping a multi-megabyte block with regular stores evicts cache lines the program still needs, to fill them with bytes
+/// nobody reads again. Blocks of at least [`threshold`](Self::with_threshold) bytes are instead streamed with AVX2 when the
+/// CPU supports it (detected at runtime with feature "std"), or SSE2 otherwise, followed by an `sfence`. Smaller blocks and
+/// the unaligned edges of large ones go through [`FnPtrMemset`].
+#[cfg(target_arch = "x86_64")]
+#[derive(Clone, Copy, Debug)]
+pub struct NonTemporal {
+    threshold: usize,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl NonTemporal {
+    /// Blocks below this size are cheap to wipe through the cache, and likely to be reused while still in it.
+    pub const DEFAULT_THRESHOLD: usize = 256 * 1024;
+
+    /// Streams blocks of at least [`DEFAULT_THRESHOLD`](Self::DEFAULT_THRESHOLD) bytes.
+    pub const fn new() -> Self {
+        Self::with_threshold(Self::DEFAULT_THRESHOLD)
+    }
+
+    /// Streams blocks of at least `threshold` bytes.
+    pub const fn with_threshold(threshold: usize) -> Self {
+        Self { threshold }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Default for NonTemporal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Wiper for NonTemporal {
+    #[inline]
+    unsafe fn wipe(&self, ptr: *mut u8, len: usize, pattern: u8) {
+        use core::arch::x86_64::_mm_sfence;
+
+        const ALIGN: usize = 32;
+        let head = ptr.align_offset(ALIGN);
+        if len < self.threshold || len < head + ALIGN {
+            return FnPtrMemset.wipe(ptr, len, pattern);
+        }
+        let body = (len - head) & !(ALIGN - 1);
+
+        FnPtrMemset.wipe(ptr, head, pattern);
+        if avx2_detected() {
+            stream_avx2(ptr.add(head), body, pattern);
+        } else {
+            stream_sse2(ptr.add(head), body, pattern);
+        }
+        FnPtrMemset.wipe(ptr.add(head + body), len - head - body, pattern);
+
+        // Streaming stores are weakly ordered, and `sfence` makes them visible before the block is handed back. It is no
+        // barrier to the compiler though, which treats it as not touching memory: what keeps the stores from being dropped
+        // is the tail wipe above, whose opaque call through `WIPER` may read the block. `tests/codegen.rs` checks this.
+        _mm_sfence();
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", feature = "std"))]
+#[inline]
+fn avx2_detected() -> bool {
+    std::is_x86_feature_detected!("avx2")
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+#[inline]
+fn avx2_detected() -> bool {
+    cfg!(target_feature = "avx2")
+}
+
+// SAFETY: `ptr` must be 32-byte aligned and valid for writes of `len` bytes, a multiple of 32
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn stream_avx2(ptr: *mut u8, len: usize, pattern: u8) {
+    use core::arch::x86_64::{__m256i, _mm256_set1_epi8, _mm256_stream_si256};
+
+    let fill = _mm256_set1_epi8(pattern as i8);
+    for offset in (0..len).step_by(32) {
+        _mm256_stream_si256(ptr.add(offset).cast::<__m256i>(), fill);
+    }
+}
+
+// SAFETY: `ptr` must be 16-byte aligned and valid for writes of `len` bytes, a multiple of 16
+#[cfg(target_arch = "x86_64")]
+unsafe fn stream_sse2(ptr: *mut u8, len: usize, pattern: u8) {
+    use core::arch::x86_64::{__m128i, _mm_set1_epi8, _mm_stream_si128};
+
+    let fill = _mm_set1_epi8(pattern as i8);
+    for offset in (0..len).step_by(16) {
+        _mm_stream_si128(ptr.add(offset).cast::<__m128i>(), fill);
+    }
+}
@@ -12,3 +12,16 @@ fn can_alloc() {
     allocation_2.resize(2048, 0xFF);
     drop(allocation_2); // Cannot check if zeroed post-drop without UB
 }
+
+#[test]
+fn can_realloc() {
+    let mut allocation = core::hint::black_box(Vec::<u8>::with_capacity(16));
+    allocation.extend_from_slice(&[0xAA; 16]);
+    allocation.resize(4096, 0xBB); // Grows by moving, since resizing in place isn't enabled
+    assert!(allocation[..16].iter().all(|&b| b == 0xAA));
+    assert!(allocation[16..].iter().all(|&b| b == 0xBB));
+
+    allocation.truncate(8);
+    allocation.shrink_to_fit();
+    assert_eq!(allocation, [0xAA; 8]);
+}
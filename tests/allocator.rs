@@ -0,0 +1,170 @@
+#![cfg(any(feature = "allocator_api", feature = "allocator-api2"))]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+// `std`'s and `allocator-api2`'s `Allocator` and `Vec` share the same shape, so the same tests are stamped out for
+// whichever of the two are enabled.
+macro_rules! allocator_tests {
+    ($($alloc:ident)::+, $($vec:ident)::+) => {
+        use $($alloc)::+::{AllocError, Allocator, Global, Layout};
+        use $($vec)::+::Vec;
+        use core::ptr::NonNull;
+        use std::sync::Mutex;
+        use core::cell::Cell;
+        use zeroizing_alloc::{ResizeInPlace, ZeroAlloc};
+
+        /// Forwards to `Global`, recording the contents of every block handed back to it.
+        #[derive(Default)]
+        struct Recording(Mutex<std::vec::Vec<std::vec::Vec<u8>>>);
+
+        unsafe impl Allocator for Recording {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                let bytes = core::slice::from_raw_parts(ptr.as_ptr(), layout.size()).to_vec();
+                self.0.lock().unwrap().push(bytes);
+                Global.deallocate(ptr, layout);
+            }
+        }
+
+        /// Refuses to resize blocks in place, and runs out of memory once `exhausted` is set.
+        #[derive(Default)]
+        struct Exhausted {
+            exhausted: Cell<bool>,
+        }
+
+        unsafe impl Allocator for Exhausted {
+            fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+                if self.exhausted.get() {
+                    return Err(AllocError);
+                }
+                Global.allocate(layout)
+            }
+
+            unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+                Global.deallocate(ptr, layout);
+            }
+        }
+
+        unsafe impl ResizeInPlace for Exhausted {
+            unsafe fn can_resize_in_place(&self, _ptr: *mut u8, _layout: Layout, _new_size: usize) -> bool {
+                false
+            }
+
+            unsafe fn resize_in_place(&self, _ptr: *mut u8, _layout: Layout, _new_size: usize) -> bool {
+                false
+            }
+        }
+
+        #[test]
+        fn can_use_as_collection_allocator() {
+            let mut secret = Vec::with_capacity_in(4, ZeroAlloc::new(Global));
+            secret.extend_from_slice(b"hunter2");
+            secret.resize(1024, 0xAA);
+            secret.truncate(3);
+            secret.shrink_to_fit();
+            assert_eq!(secret.as_slice(), b"hun");
+        }
+
+        #[test]
+        fn wipes_on_deallocate_and_grow() {
+            let alloc = ZeroAlloc::new(Recording::default());
+
+            let mut secret = Vec::with_capacity_in(8, &alloc);
+            secret.extend_from_slice(&[0xAA; 8]);
+            secret.reserve(64); // Moves the block, as `Recording` can't grow in place
+            assert_eq!(secret.as_slice(), [0xAA; 8]);
+            drop(secret);
+
+            let released = alloc.inner().0.lock().unwrap();
+            assert_eq!(released.len(), 2);
+            assert!(released.iter().flatten().all(|&b| b == 0));
+        }
+
+        #[test]
+        fn failed_shrink_keeps_contents() {
+            let alloc = ZeroAlloc::new(Exhausted::default()).with_resize_in_place();
+            let layout = Layout::from_size_align(64, 8).unwrap();
+            unsafe {
+                let block = alloc.allocate(layout).unwrap().cast::<u8>();
+                block.as_ptr().write_bytes(0xAA, 64);
+                alloc.inner().exhausted.set(true);
+                let shrunk = alloc.shrink(block, layout, Layout::from_size_align(16, 8).unwrap());
+                assert!(shrunk.is_err());
+                assert_eq!(core::slice::from_raw_parts(block.as_ptr(), 64), [0xAA; 64]);
+                alloc.deallocate(block, layout);
+            }
+        }
+
+        #[cfg(feature = "canaries")]
+        #[test]
+        fn no_canaries_around_blocks() {
+            // Canaries only apply to `GlobalAlloc`, so blocks allocated here aren't padded with redzones
+            let alloc = ZeroAlloc::new(Recording::default())
+                .with_canary_hook(|_, _| unreachable!("no redzones to overwrite"));
+            let layout = Layout::from_size_align(3, 1).unwrap();
+            unsafe {
+                let block = alloc.allocate(layout).unwrap();
+                block.cast::<u8>().as_ptr().write_bytes(0xAA, 3);
+                alloc.deallocate(block.cast(), layout);
+            }
+
+            let released = alloc.inner().0.lock().unwrap();
+            assert_eq!(*released, [[0; 3]]);
+        }
+
+        #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+        #[test]
+        fn zero_sized_blocks_skip_usable_size() {
+            // `System` hands these out as dangling pointers, which `malloc_usable_size` must never see
+            let alloc = ZeroAlloc::new(std::alloc::System).with_usable_size();
+            let layout = Layout::new::<()>();
+            unsafe {
+                let block = alloc.allocate(layout).unwrap();
+                alloc.deallocate(block.cast(), layout);
+            }
+        }
+
+        #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+        #[test]
+        fn grows_zero_sized_blocks_into_fresh_ones() {
+            // The dangling pointer must never reach `malloc_usable_size`
+            let alloc = ZeroAlloc::new(std::alloc::System).with_resize_in_place();
+            let empty = Layout::from_size_align(0, 8).unwrap();
+            let layout = Layout::from_size_align(16, 8).unwrap();
+            unsafe {
+                let block = alloc.allocate(empty).unwrap().cast::<u8>();
+                let grown = alloc.grow(block, empty, layout).unwrap();
+                assert_eq!(grown.len(), 16);
+                alloc.deallocate(grown.cast(), layout);
+            }
+        }
+
+        #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+        #[test]
+        fn shrinks_to_zero_size_by_releasing_the_block() {
+            let alloc = ZeroAlloc::new(std::alloc::System).with_resize_in_place();
+            let layout = Layout::from_size_align(16, 8).unwrap();
+            let empty = Layout::from_size_align(0, 8).unwrap();
+            unsafe {
+                let block = alloc.allocate(layout).unwrap().cast::<u8>();
+                let shrunk = alloc.shrink(block, layout, empty).unwrap();
+                assert_eq!(shrunk.len(), 0);
+                // Kept in place, the block would leak: deallocating a zero-sized block hands nothing back
+                assert_ne!(shrunk.cast::<u8>(), block);
+                alloc.deallocate(shrunk.cast(), empty);
+            }
+        }
+    };
+}
+
+#[cfg(feature = "allocator_api")]
+mod allocator_api {
+    allocator_tests!(std::alloc, std::vec);
+}
+
+#[cfg(feature = "allocator-api2")]
+mod allocator_api2 {
+    allocator_tests!(allocator_api2::alloc, allocator_api2::vec);
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "canaries")]
+
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use support::{filled, layout, released, InspectingAlloc};
+use zeroizing_alloc::ZeroAlloc;
+
+// Canaries which are expected to stay intact
+fn canaries() -> ZeroAlloc<InspectingAlloc> {
+    ZeroAlloc::new(InspectingAlloc::new())
+        .with_canary_hook(|_, _| unreachable!("redzones were overwritten"))
+}
+
+#[test]
+fn intact_blocks_are_freed_with_their_redzones() {
+    let alloc = canaries();
+    unsafe {
+        let ptr = filled(&alloc, 24, 0xAA);
+        alloc.dealloc(ptr, layout(24));
+    }
+    let released = released(&alloc);
+    assert_eq!(released.len(), 1);
+    let (front, rest) = released[0].split_at(16);
+    let (block, back) = rest.split_at(24);
+    assert_eq!(block, [0; 24]);
+    assert_eq!(back.len(), 16);
+    assert!(front.iter().chain(back).all(|&b| b == front[0] && b != 0));
+}
+
+#[test]
+fn overrun_calls_hook() {
+    static CORRUPTED: AtomicUsize = AtomicUsize::new(0);
+    fn hook(ptr: *mut u8, layout: Layout) {
+        assert_eq!(layout.size(), 24);
+        CORRUPTED.store(ptr.addr(), Ordering::Relaxed);
+    }
+
+    let alloc = ZeroAlloc::new(InspectingAlloc::new()).with_canary_hook(hook);
+    let ptr = unsafe {
+        let ptr = filled(&alloc, 24, 0xAA);
+        ptr.add(24).write(0xAA);
+        alloc.dealloc(ptr, layout(24));
+        ptr
+    };
+    assert_eq!(CORRUPTED.load(Ordering::Relaxed), ptr.addr());
+    // The redzones are wiped along with the block, in case the overrun left secrets there
+    assert_eq!(released(&alloc), [vec![0; 16 + 24 + 16]]);
+}
+
+#[test]
+fn underrun_calls_hook() {
+    static CORRUPTED: AtomicUsize = AtomicUsize::new(0);
+    fn hook(_ptr: *mut u8, _layout: Layout) {
+        CORRUPTED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let alloc = ZeroAlloc::new(InspectingAlloc::new()).with_canary_hook(hook);
+    unsafe {
+        let ptr = filled(&alloc, 24, 0xAA);
+        ptr.sub(1).write(0xAA);
+        alloc.dealloc(ptr, layout(24));
+    }
+    assert_eq!(CORRUPTED.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn blocks_stay_aligned() {
+    let alloc = canaries();
+    let aligned = Layout::from_size_align(100, 64).unwrap();
+    unsafe {
+        let ptr = alloc.alloc(aligned);
+        assert_eq!(ptr.addr() % 64, 0);
+        ptr.write_bytes(0xAA, 100);
+        alloc.dealloc(ptr, aligned);
+    }
+    assert_eq!(released(&alloc)[0].len(), 64 + 100 + 16);
+}
+
+#[test]
+fn realloc_moves_blocks() {
+    let alloc = canaries().with_resize_in_place();
+    unsafe {
+        let ptr = filled(&alloc, 16, 0xAA);
+        let grown = alloc.realloc(ptr, layout(16), 64);
+        assert_ne!(grown, ptr);
+        assert_eq!(core::slice::from_raw_parts(grown, 16), [0xAA; 16]);
+        alloc.dealloc(grown, layout(64));
+    }
+    assert_eq!(released(&alloc).len(), 2);
+}
+
+#[cfg(all(feature = "std", unix))]
+#[test]
+fn overrun_aborts_by_default() {
+    if support::is_child() {
+        let alloc = ZeroAlloc::new(std::alloc::System).with_canaries();
+        unsafe {
+            let ptr = alloc.alloc(layout(24));
+            ptr.add(24).write(0);
+            alloc.dealloc(ptr, layout(24));
+        }
+        return;
+    }
+
+    use std::os::unix::process::ExitStatusExt;
+    let status = support::run_in_child("overrun_aborts_by_default").status;
+    // SIGABRT
+    assert_eq!(status.signal(), Some(6));
+}
+
+#[cfg(all(feature = "std", unix))]
+#[test]
+fn panicking_hook_aborts() {
+    if support::is_child() {
+        let alloc = ZeroAlloc::new(std::alloc::System).with_canary_hook(|_, _| panic!("corrupted"));
+        unsafe {
+            let ptr = alloc.alloc(layout(24));
+            ptr.add(24).write(0);
+            alloc.dealloc(ptr, layout(24));
+        }
+        return;
+    }
+
+    use std::os::unix::process::ExitStatusExt;
+    let status = support::run_in_child("panicking_hook_aborts").status;
+    // SIGABRT, rather than a panic unwinding out of `dealloc`
+    assert_eq!(status.signal(), Some(6));
+}
@@ -0,0 +1,184 @@
+//! Checks the optimized `dealloc` still wipes through `WIPER`, so a compiler upgrade can't silently elide it.
+//!
+//! These build the crate again in release mode and disassemble the result, so they need `objdump` (from binutils) and fail
+//! without it.
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+fn run(cmd: &mut Command) -> String {
+    let output = cmd
+        .output()
+        .unwrap_or_else(|e| panic!("failed to run {cmd:?}: {e}"));
+    assert!(
+        output.status.success(),
+        "{cmd:?} failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Builds the probe against an optimized build of the crate, once for all tests.
+fn probe() -> &'static Path {
+    static PROBE: OnceLock<PathBuf> = OnceLock::new();
+    PROBE.get_or_init(|| {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("codegen");
+
+        // A separate target dir keeps this from contending with the outer build. No features, so `WIPER` is the default.
+        run(Command::new(env!("CARGO"))
+            .args(["build", "--release", "--lib", "--manifest-path"])
+            .arg(manifest_dir.join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&out_dir));
+        let rlib = out_dir.join("release/libzeroizing_alloc.rlib");
+        let probe = out_dir.join("probe");
+        run(
+            Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into()))
+                // Keeps the default relocation model, so this checks the position-independent code that gets shipped
+                .args(["--edition=2021", "--crate-type=bin", "-Copt-level=3"])
+                .arg(format!("--extern=zeroizing_alloc={}", rlib.display()))
+                .arg("-o")
+                .arg(&probe)
+                .arg(manifest_dir.join("tests/codegen/probe.rs")),
+        );
+        probe
+    })
+}
+
+// Returns the disassembly of `symbol` in the probe, one instruction per line.
+fn disassemble(symbol: &str) -> Vec<String> {
+    // Checked up front, so a missing `objdump` fails with a hint rather than a bare "not found"
+    assert!(
+        Command::new("objdump").arg("--version").output().is_ok(),
+        "objdump not found, install binutils to run the codegen tests"
+    );
+    let disassembly = run(Command::new("objdump")
+        .arg(format!("--disassemble={symbol}"))
+        .arg("--no-show-raw-insn")
+        .arg(probe()));
+    let lines: Vec<String> = disassembly
+        .lines()
+        .skip_while(|line| !line.contains(&format!("<{symbol}>:")))
+        .map(String::from)
+        .collect();
+    assert!(!lines.is_empty(), "{symbol} not found in:\n{disassembly}");
+    lines
+}
+
+// Maps the addresses of the probe's functions and statics to their (mangled) names, and those of the GOT slots
+// position-independent code reads their addresses from to the same names suffixed with `@GOT`.
+fn symbols() -> &'static HashMap<u64, String> {
+    static SYMBOLS: OnceLock<HashMap<u64, String>> = OnceLock::new();
+    SYMBOLS.get_or_init(|| {
+        let hex = |s: &str| u64::from_str_radix(s, 16).ok();
+        let table = run(Command::new("objdump").arg("--syms").arg(probe()));
+        let mut symbols: HashMap<u64, String> = table
+            .lines()
+            .filter(|line| line.contains(" F ") || line.contains(" O "))
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                Some((hex(fields.first()?)?, fields.last()?.to_string()))
+            })
+            .collect();
+
+        // The GOT slots are filled in at load time, by relocations of the form `<slot> R_X86_64_RELATIVE *ABS*+<target>`
+        let relocations = run(Command::new("objdump").arg("--dynamic-reloc").arg(probe()));
+        let slots: Vec<(u64, String)> = relocations
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let slot = hex(fields.next()?)?;
+                let target = hex(fields.nth(1)?.strip_prefix("*ABS*+0x")?)?;
+                Some((slot, format!("{}@GOT", symbols.get(&target)?)))
+            })
+            .collect();
+        symbols.extend(slots);
+        symbols
+    })
+}
+
+// Returns the symbol an instruction refers to: the target of a direct call, or what a RIP-relative operand resolves to,
+// directly or through the GOT.
+fn referenced_symbol(line: &str) -> Option<&'static str> {
+    // objdump annotates RIP-relative operands with the address they resolve to, as in `mov 0x3f793(%rip),%rax  # 533a0 <...>`,
+    // and direct calls with their target, as in `call 13c00 <...>`
+    let address = match line.split_once('#') {
+        Some((_, annotation)) => annotation.split_whitespace().next()?,
+        None => {
+            let mut fields = line.split_whitespace().rev();
+            fields.next().filter(|name| name.starts_with('<'))?;
+            fields.next()?
+        }
+    };
+    let address = u64::from_str_radix(address, 16).ok()?;
+    symbols().get(&address).map(String::as_str)
+}
+
+// Returns whether the instruction refers to `WIPER` itself, or to its GOT slot if `got` is set.
+fn refers_to_wiper(line: &str, got: bool) -> bool {
+    referenced_symbol(line)
+        .is_some_and(|symbol| symbol.contains("WIPER") && symbol.ends_with("@GOT") == got)
+}
+
+// Asserts that `WIPER` is loaded and called through at some point after instruction `from`.
+fn assert_calls_wiper_after(lines: &[String], from: usize) {
+    // Position-independent code reads the address of `WIPER` from its GOT slot into a register first, possibly well
+    // before the volatile load through that register
+    let registers: Vec<&str> = lines
+        .iter()
+        .filter(|line| line.contains("mov") && refers_to_wiper(line, true))
+        .filter_map(|line| Some(line.split_once('#')?.0.rsplit_once(',')?.1.trim()))
+        .collect();
+    let load = lines[from..]
+        .iter()
+        .position(|line| {
+            line.contains("mov")
+                && (refers_to_wiper(line, false)
+                    || registers
+                        .iter()
+                        .any(|register| line.contains(&format!("({register}),"))))
+        })
+        .map(|i| from + i)
+        .unwrap_or_else(|| panic!("no load of `WIPER` in:\n{}", lines.join("\n")));
+    assert!(
+        lines[load..]
+            .iter()
+            .any(|line| line.contains("call   *%") || line.contains("call   *0x")),
+        "no indirect call after loading `WIPER` in:\n{}",
+        lines.join("\n")
+    );
+}
+
+#[test]
+fn release_dealloc_calls_wiper() {
+    assert_calls_wiper_after(&disassemble("zeroizing_dealloc_probe"), 0);
+}
+
+#[test]
+fn release_nontemporal_dealloc_keeps_streaming_stores() {
+    let lines = disassemble("nontemporal_dealloc_probe");
+    // The streaming loop is either inlined, or called by its (mangled) name
+    let streaming = |line: &String| {
+        line.contains("movntdq")
+            || referenced_symbol(line).is_some_and(|symbol| symbol.contains("stream_sse2"))
+    };
+    let last_store = lines
+        .iter()
+        .rposition(streaming)
+        .unwrap_or_else(|| panic!("no streaming stores in:\n{}", lines.join("\n")));
+    if let Some(callee) = referenced_symbol(&lines[last_store]) {
+        let callee = callee.trim_end_matches("@GOT");
+        assert!(
+            disassemble(callee)
+                .iter()
+                .any(|line| line.contains("movntdq")),
+            "no streaming store in {callee}"
+        );
+    }
+    // `sfence` doesn't stop the compiler from dropping the stores, only the call through `WIPER` for the tail does
+    assert_calls_wiper_after(&lines, last_store);
+}
@@ -0,0 +1,35 @@
+// Compiled with `-Copt-level=3` by `tests/codegen.rs`, which disassembles `zeroizing_dealloc_probe` and
+// `nontemporal_dealloc_probe`.
+
+use core::alloc::{GlobalAlloc, Layout};
+use std::alloc::System;
+use zeroizing_alloc::{NonTemporal, ZeroAlloc};
+
+static ALLOC: ZeroAlloc<System> = ZeroAlloc::new(System);
+static NON_TEMPORAL: ZeroAlloc<System, NonTemporal> =
+    ZeroAlloc::with_wiper(System, NonTemporal::with_threshold(0));
+
+#[no_mangle]
+#[inline(never)]
+pub unsafe fn zeroizing_dealloc_probe(ptr: *mut u8, layout: Layout) {
+    ALLOC.dealloc(ptr, layout);
+}
+
+#[no_mangle]
+#[inline(never)]
+pub unsafe fn nontemporal_dealloc_probe(ptr: *mut u8, layout: Layout) {
+    NON_TEMPORAL.dealloc(ptr, layout);
+}
+
+fn main() {
+    let layout = Layout::new::<[u8; 64]>();
+    unsafe {
+        let secret = ALLOC.alloc(layout);
+        secret.write_bytes(0xAA, layout.size());
+        zeroizing_dealloc_probe(secret, layout);
+
+        let secret = NON_TEMPORAL.alloc(layout);
+        secret.write_bytes(0xAA, layout.size());
+        nontemporal_dealloc_probe(secret, layout);
+    }
+}
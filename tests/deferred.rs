@@ -0,0 +1,71 @@
+#![cfg(feature = "std")]
+
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use std::time::{Duration, Instant};
+use support::SharedAlloc;
+use zeroizing_alloc::{DeferQueue, ZeroAlloc};
+
+const CAPACITY: usize = 8;
+
+fn deferring(min_size: usize) -> &'static ZeroAlloc<SharedAlloc> {
+    let queue = Box::leak(Box::new(DeferQueue::<CAPACITY>::new()));
+    Box::leak(Box::new(
+        ZeroAlloc::new(SharedAlloc::new()).with_deferred_wipe(queue, min_size),
+    ))
+}
+
+unsafe fn free(alloc: &ZeroAlloc<SharedAlloc>, size: usize, byte: u8) {
+    let layout = Layout::from_size_align(size, 8).unwrap();
+    let ptr = alloc.alloc(layout);
+    assert!(!ptr.is_null());
+    ptr.write_bytes(byte, size);
+    alloc.dealloc(ptr, layout);
+}
+
+#[test]
+fn worker_wipes_large_blocks() {
+    let alloc = deferring(1024);
+    alloc.spawn_deferred_wiper().unwrap();
+    unsafe { free(alloc, 4096, 0xAA) };
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while alloc.inner().released().is_empty() {
+        assert!(
+            Instant::now() < deadline,
+            "the worker never released the block"
+        );
+        std::thread::yield_now();
+    }
+    assert_eq!(alloc.inner().released(), [vec![0; 4096]]);
+}
+
+#[test]
+fn small_blocks_are_wiped_synchronously() {
+    let alloc = deferring(1024);
+    alloc.spawn_deferred_wiper().unwrap();
+    unsafe { free(alloc, 64, 0xAA) };
+    assert_eq!(alloc.inner().released(), [vec![0; 64]]);
+}
+
+#[test]
+fn frees_are_synchronous_without_worker() {
+    let alloc = deferring(1024);
+    unsafe { free(alloc, 4096, 0xAA) };
+    assert_eq!(alloc.inner().released(), [vec![0; 4096]]);
+}
+
+#[test]
+fn flush_waits_for_every_block() {
+    let alloc = deferring(16);
+    alloc.spawn_deferred_wiper().unwrap();
+    for _ in 0..2 * CAPACITY {
+        unsafe { free(alloc, 64, 0xAA) };
+    }
+    alloc.flush_deferred();
+
+    let released = alloc.inner().released();
+    assert_eq!(released.len(), 2 * CAPACITY);
+    assert!(released.iter().all(|block| block == &[0; 64]));
+}
@@ -0,0 +1,27 @@
+#![cfg(all(
+    feature = "dont-dump",
+    feature = "guard-pages",
+    any(target_os = "linux", target_os = "android")
+))]
+
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use support::vm_flags;
+use zeroizing_alloc::{DontDumpAlloc, GuardedAlloc, ZeroAlloc};
+
+// Tests shared with `LockedAlloc` are in `tests/pages.rs`
+
+#[test]
+fn dedicated_pages_are_wiped_on_fork() {
+    let alloc = ZeroAlloc::new(DontDumpAlloc::new(GuardedAlloc).with_wipe_on_fork());
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    unsafe {
+        let ptr = alloc.alloc(layout);
+        let flags = vm_flags(ptr);
+        assert!(flags.contains(&"dd".into()));
+        assert!(flags.contains(&"wf".into()));
+        alloc.dealloc(ptr, layout);
+    }
+    assert_eq!(alloc.inner().errors(), 0);
+}
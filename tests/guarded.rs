@@ -0,0 +1,77 @@
+#![cfg(all(feature = "guard-pages", unix))]
+
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use std::os::unix::process::ExitStatusExt;
+use support::{is_child, run_in_child};
+use zeroizing_alloc::{GuardedAlloc, ZeroAlloc};
+
+static ALLOC: ZeroAlloc<GuardedAlloc> = ZeroAlloc::new(GuardedAlloc).with_usable_size();
+
+const PAGE: usize = 4096;
+
+#[test]
+fn blocks_end_at_guard_page() {
+    for (size, align) in [(1, 1), (13, 8), (4096, 16), (5000, 64), (3 * PAGE, PAGE)] {
+        let layout = Layout::from_size_align(size, align).unwrap();
+        unsafe {
+            let ptr = ALLOC.alloc(layout);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr.addr() % align, 0);
+            let end = ptr.addr() + size;
+            assert!(
+                end.next_multiple_of(PAGE) - end < align,
+                "{size} bytes end {end:#x}"
+            );
+
+            ptr.write_bytes(0xAA, size);
+            ALLOC.dealloc(ptr, layout);
+        }
+    }
+}
+
+#[test]
+fn realloc_keeps_contents() {
+    let layout = Layout::from_size_align(100, 8).unwrap();
+    unsafe {
+        let ptr = ALLOC.alloc(layout);
+        ptr.write_bytes(0xAA, 100);
+        let grown = ALLOC.realloc(ptr, layout, 10_000);
+        assert_eq!(core::slice::from_raw_parts(grown, 100), [0xAA; 100]);
+        ALLOC.dealloc(grown, Layout::from_size_align(10_000, 8).unwrap());
+    }
+}
+
+#[test]
+fn overflow_faults() {
+    if is_child() {
+        let layout = Layout::from_size_align(100, 1).unwrap();
+        unsafe {
+            let ptr = ALLOC.alloc(layout);
+            ptr.add(100).write_volatile(0xAA);
+        }
+        return;
+    }
+    assert_eq!(
+        run_in_child("overflow_faults").status.signal(),
+        Some(libc::SIGSEGV)
+    );
+}
+
+#[test]
+fn use_after_free_faults() {
+    if is_child() {
+        let layout = Layout::from_size_align(100, 1).unwrap();
+        unsafe {
+            let ptr = ALLOC.alloc(layout);
+            ALLOC.dealloc(ptr, layout);
+            ptr.read_volatile();
+        }
+        return;
+    }
+    assert_eq!(
+        run_in_child("use_after_free_faults").status.signal(),
+        Some(libc::SIGSEGV)
+    );
+}
@@ -0,0 +1,55 @@
+#![cfg(all(feature = "lock-pages", unix))]
+
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use std::alloc::System;
+use support::isolated;
+use zeroizing_alloc::{LockedAlloc, ZeroAlloc};
+
+// Tests shared with `DontDumpAlloc` are in `tests/pages.rs`. Pages are counted across every `LockedAlloc` in the process,
+// so tests checking counts each run in a process of their own.
+
+#[cfg(target_os = "linux")]
+#[test]
+fn locked_pages_count_towards_the_process() {
+    if !isolated("locked_pages_count_towards_the_process") {
+        return;
+    }
+    let alloc = ZeroAlloc::new(LockedAlloc::new(System));
+    let layout = Layout::from_size_align(3 * 4096, 4096).unwrap();
+    unsafe {
+        let ptr = alloc.alloc(layout);
+        assert!(!ptr.is_null());
+        assert!(locked_kib() >= 12);
+        alloc.dealloc(ptr, layout);
+    }
+}
+
+#[test]
+fn blocks_which_cannot_be_locked_are_counted() {
+    if !isolated("blocks_which_cannot_be_locked_are_counted") {
+        return;
+    }
+    // More pages than either `RLIMIT_MEMLOCK` allows or the page table can track
+    let alloc = LockedAlloc::new(System);
+    let layout = Layout::from_size_align(32 << 20, 4096).unwrap();
+    unsafe {
+        let ptr = alloc.alloc(layout);
+        assert!(!ptr.is_null());
+        ptr.write_bytes(0xAA, layout.size());
+        alloc.dealloc(ptr, layout);
+    }
+    assert!(alloc.errors() > 0);
+    assert_eq!(alloc.locked_pages(), 0);
+}
+
+#[cfg(target_os = "linux")]
+fn locked_kib() -> usize {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let line = status
+        .lines()
+        .find(|line| line.starts_with("VmLck:"))
+        .unwrap();
+    line.split_whitespace().nth(1).unwrap().parse().unwrap()
+}
@@ -0,0 +1,127 @@
+//! Tests shared by `LockedAlloc` and `DontDumpAlloc`, which count the blocks on each page the same way.
+#![cfg(any(
+    all(feature = "lock-pages", unix),
+    all(feature = "dont-dump", any(target_os = "linux", target_os = "android"))
+))]
+
+mod support;
+
+// Pages are counted across every allocator of a kind in the process, so tests checking counts or overflowing the table each
+// run in a process of their own. `$flag` is how `/proc/self/smaps` shows a page set up by `$alloc`.
+macro_rules! page_tests {
+    ($module:ident, $alloc:ident, $pages:ident, $flag:literal) => {
+        mod $module {
+            use crate::support::{isolated, InspectingAlloc};
+            use core::alloc::{GlobalAlloc, Layout};
+            use std::alloc::System;
+            use zeroizing_alloc::{$alloc, ZeroAlloc};
+
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            fn is_set_up(ptr: *mut u8) -> bool {
+                crate::support::vm_flags(ptr).contains(&$flag.into())
+            }
+
+            #[test]
+            fn pages_are_set_up_until_freed() {
+                if !isolated(concat!(
+                    stringify!($module),
+                    "::pages_are_set_up_until_freed"
+                )) {
+                    return;
+                }
+                let alloc = ZeroAlloc::new($alloc::new(System));
+                let layout = Layout::from_size_align(2 * 4096, 4096).unwrap();
+                unsafe {
+                    let ptr = alloc.alloc(layout);
+                    assert!(!ptr.is_null());
+                    assert_eq!(alloc.inner().$pages(), 2);
+                    #[cfg(any(target_os = "linux", target_os = "android"))]
+                    assert!(is_set_up(ptr));
+
+                    alloc.dealloc(ptr, layout);
+                }
+                assert_eq!(alloc.inner().$pages(), 0);
+                assert_eq!(alloc.inner().errors(), 0);
+            }
+
+            #[test]
+            fn shared_page_stays_set_up_until_last_block_is_freed() {
+                if !isolated(concat!(
+                    stringify!($module),
+                    "::shared_page_stays_set_up_until_last_block_is_freed"
+                )) {
+                    return;
+                }
+                let alloc = $alloc::new(InspectingAlloc::new());
+                let layout = Layout::from_size_align(16, 8).unwrap();
+                unsafe {
+                    let a = alloc.alloc(layout);
+                    let b = alloc.alloc(layout);
+                    assert_eq!(alloc.$pages(), 1);
+
+                    alloc.dealloc(a, layout);
+                    assert_eq!(alloc.$pages(), 1);
+                    #[cfg(any(target_os = "linux", target_os = "android"))]
+                    assert!(is_set_up(b));
+                    alloc.dealloc(b, layout);
+                    #[cfg(any(target_os = "linux", target_os = "android"))]
+                    assert!(!is_set_up(b));
+                }
+                assert_eq!(alloc.$pages(), 0);
+            }
+
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            #[test]
+            fn shared_page_stays_set_up_once_the_table_overflowed() {
+                if !isolated(concat!(
+                    stringify!($module),
+                    "::shared_page_stays_set_up_once_the_table_overflowed"
+                )) {
+                    return;
+                }
+
+                // Small blocks are bumped from one arena, so they share its first page
+                let arena = InspectingAlloc::new();
+                let (small_alloc, large_alloc) = ($alloc::new(&arena), $alloc::new(System));
+                let small = Layout::from_size_align(16, 8).unwrap();
+                // Spans as many pages as the table can track
+                let filler = Layout::from_size_align(4096 * 4096, 4096).unwrap();
+                unsafe {
+                    let filled = large_alloc.alloc(filler);
+                    let untracked = small_alloc.alloc(small);
+                    large_alloc.dealloc(filled, filler);
+
+                    // The page is counted for this block alone, which must not tear it down when the other one is freed
+                    let tracked = small_alloc.alloc(small);
+                    assert!(is_set_up(tracked));
+                    small_alloc.dealloc(untracked, small);
+                    assert!(is_set_up(tracked));
+                    small_alloc.dealloc(tracked, small);
+                }
+                assert!(small_alloc.errors() > 0);
+            }
+
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            #[test]
+            fn shared_page_stays_set_up_until_every_allocator_freed_its_block() {
+                let arena = InspectingAlloc::new();
+                let (a, b) = ($alloc::new(&arena), $alloc::new(&arena));
+                let layout = Layout::from_size_align(16, 8).unwrap();
+                unsafe {
+                    let x = a.alloc(layout);
+                    let y = b.alloc(layout);
+                    a.dealloc(x, layout);
+                    assert!(is_set_up(y));
+                    b.dealloc(y, layout);
+                    assert!(!is_set_up(y));
+                }
+            }
+        }
+    };
+}
+
+#[cfg(all(feature = "lock-pages", unix))]
+page_tests!(locked, LockedAlloc, locked_pages, "lo");
+
+#[cfg(all(feature = "dont-dump", any(target_os = "linux", target_os = "android")))]
+page_tests!(dont_dump, DontDumpAlloc, marked_pages, "dd");
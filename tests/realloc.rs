@@ -0,0 +1,142 @@
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::Cell;
+use support::{filled, released, InspectingAlloc};
+use zeroizing_alloc::{ResizeInPlace, ZeroAlloc};
+
+/// Refuses to resize blocks in place, and runs out of memory once `exhausted` is set.
+struct Exhausted {
+    inner: InspectingAlloc,
+    exhausted: Cell<bool>,
+}
+
+unsafe impl GlobalAlloc for Exhausted {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if self.exhausted.get() {
+            return core::ptr::null_mut();
+        }
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+}
+
+unsafe impl ResizeInPlace for Exhausted {
+    unsafe fn can_resize_in_place(&self, _ptr: *mut u8, _layout: Layout, _new_size: usize) -> bool {
+        false
+    }
+
+    unsafe fn resize_in_place(&self, _ptr: *mut u8, _layout: Layout, _new_size: usize) -> bool {
+        false
+    }
+}
+
+#[test]
+fn shrink_in_place_wipes_tail() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new()).with_resize_in_place();
+    unsafe {
+        let ptr = filled(&alloc, 64, 0xAA);
+        let shrunk = alloc.realloc(ptr, Layout::from_size_align(64, 8).unwrap(), 16);
+        assert_eq!(shrunk, ptr);
+        assert_eq!(core::slice::from_raw_parts(shrunk, 16), [0xAA; 16]);
+    }
+    assert_eq!(released(&alloc), [vec![0; 48]]);
+}
+
+#[test]
+fn failed_shrink_keeps_contents() {
+    let alloc = ZeroAlloc::new(Exhausted {
+        inner: InspectingAlloc::new(),
+        exhausted: Cell::new(false),
+    })
+    .with_resize_in_place();
+    unsafe {
+        let ptr = filled(&alloc, 64, 0xAA);
+        alloc.inner().exhausted.set(true);
+        let shrunk = alloc.realloc(ptr, Layout::from_size_align(64, 8).unwrap(), 16);
+        assert!(shrunk.is_null());
+        assert_eq!(core::slice::from_raw_parts(ptr, 64), [0xAA; 64]);
+    }
+    assert!(alloc.inner().inner.released().is_empty());
+}
+
+#[test]
+fn grow_in_place_keeps_block() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new()).with_resize_in_place();
+    unsafe {
+        let ptr = filled(&alloc, 16, 0xAA);
+        let grown = alloc.realloc(ptr, Layout::from_size_align(16, 8).unwrap(), 64);
+        assert_eq!(grown, ptr);
+        assert_eq!(core::slice::from_raw_parts(grown, 16), [0xAA; 16]);
+    }
+    assert!(released(&alloc).is_empty());
+}
+
+#[test]
+fn moved_block_is_wiped() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new()).with_resize_in_place();
+    unsafe {
+        let ptr = filled(&alloc, 16, 0xAA);
+        let _blocker = filled(&alloc, 16, 0xBB);
+        let moved = alloc.realloc(ptr, Layout::from_size_align(16, 8).unwrap(), 64);
+        assert_ne!(moved, ptr);
+        assert_eq!(core::slice::from_raw_parts(moved, 16), [0xAA; 16]);
+    }
+    assert_eq!(released(&alloc), [vec![0; 16]]);
+}
+
+#[test]
+fn shrink_without_resize_moves_and_wipes() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new());
+    unsafe {
+        let ptr = filled(&alloc, 64, 0xAA);
+        let moved = alloc.realloc(ptr, Layout::from_size_align(64, 8).unwrap(), 16);
+        assert_ne!(moved, ptr);
+        assert_eq!(core::slice::from_raw_parts(moved, 16), [0xAA; 16]);
+    }
+    assert_eq!(released(&alloc), [vec![0; 64]]);
+}
+
+#[test]
+fn moved_block_is_filled_with_pattern() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new()).with_pattern(0xDE);
+    unsafe {
+        let ptr = filled(&alloc, 16, 0xAA);
+        let moved = alloc.realloc(ptr, Layout::from_size_align(16, 8).unwrap(), 64);
+        assert_eq!(core::slice::from_raw_parts(moved, 16), [0xAA; 16]);
+    }
+    assert_eq!(released(&alloc), [vec![0xDE; 16]]);
+}
+
+#[test]
+fn wipe_on_alloc_clears_recycled_memory() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new())
+        .with_wipe_on_alloc()
+        .with_resize_in_place();
+    unsafe {
+        // The arena starts out filled with stale bytes
+        let ptr = alloc.alloc(Layout::from_size_align(16, 8).unwrap());
+        assert_eq!(core::slice::from_raw_parts(ptr, 16), [0; 16]);
+
+        ptr.write_bytes(0xAA, 16);
+        let grown = alloc.realloc(ptr, Layout::from_size_align(16, 8).unwrap(), 64);
+        assert_eq!(grown, ptr);
+        assert_eq!(core::slice::from_raw_parts(grown, 16), [0xAA; 16]);
+        assert_eq!(core::slice::from_raw_parts(grown.add(16), 48), [0; 48]);
+    }
+}
+
+#[test]
+fn max_size_skips_large_blocks() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new()).with_max_size(32);
+    unsafe {
+        let small = filled(&alloc, 32, 0xAA);
+        let large = filled(&alloc, 33, 0xBB);
+        alloc.dealloc(small, Layout::from_size_align(32, 8).unwrap());
+        alloc.dealloc(large, Layout::from_size_align(33, 8).unwrap());
+    }
+    assert_eq!(released(&alloc), [vec![0; 32], vec![0xBB; 33]]);
+}
@@ -0,0 +1,64 @@
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use support::{filled_with_layout, InspectingAlloc, STALE};
+use zeroizing_alloc::{RoutingAlloc, ZeroAlloc};
+
+fn routing() -> RoutingAlloc<ZeroAlloc<InspectingAlloc>, InspectingAlloc> {
+    RoutingAlloc::new(
+        ZeroAlloc::new(InspectingAlloc::new()),
+        InspectingAlloc::new(),
+    )
+    .with_max_small_size(64)
+    .with_max_small_align(16)
+}
+
+#[test]
+fn routes_by_size_and_alignment() {
+    let alloc = routing();
+    let small = Layout::from_size_align(64, 8).unwrap();
+    let large = Layout::from_size_align(65, 8).unwrap();
+    let aligned = Layout::from_size_align(16, 32).unwrap();
+    unsafe {
+        for layout in [small, large, aligned] {
+            let ptr = filled_with_layout(&alloc, layout, 0xAA);
+            alloc.dealloc(ptr, layout);
+        }
+    }
+    assert_eq!(alloc.small().inner().released(), [vec![0; 64]]);
+    assert_eq!(alloc.large().released(), [vec![0xAA; 65], vec![0xAA; 16]]);
+}
+
+#[test]
+fn realloc_moves_across_threshold() {
+    let alloc = routing();
+    let small = Layout::from_size_align(32, 8).unwrap();
+    let large = Layout::from_size_align(128, 8).unwrap();
+    unsafe {
+        let ptr = filled_with_layout(&alloc, small, 0xAA);
+        let grown = alloc.realloc(ptr, small, 128);
+        assert_eq!(core::slice::from_raw_parts(grown, 32), [0xAA; 32]);
+        // The large allocator hands out stale memory past the copied bytes
+        assert_eq!(core::slice::from_raw_parts(grown.add(32), 96), [STALE; 96]);
+
+        grown.write_bytes(0xBB, 128);
+        let shrunk = alloc.realloc(grown, large, 32);
+        assert_eq!(core::slice::from_raw_parts(shrunk, 32), [0xBB; 32]);
+        alloc.dealloc(shrunk, small);
+    }
+    assert_eq!(alloc.small().inner().released(), [vec![0; 32], vec![0; 32]]);
+    assert_eq!(alloc.large().released(), [vec![0xBB; 128]]);
+}
+
+#[test]
+fn realloc_within_a_side_stays_there() {
+    let alloc = routing();
+    let layout = Layout::from_size_align(16, 8).unwrap();
+    unsafe {
+        let ptr = filled_with_layout(&alloc, layout, 0xAA);
+        let moved = alloc.realloc(ptr, layout, 48);
+        alloc.dealloc(moved, Layout::from_size_align(48, 8).unwrap());
+    }
+    assert_eq!(alloc.small().inner().released(), [vec![0; 16], vec![0; 48]]);
+    assert!(alloc.large().released().is_empty());
+}
@@ -0,0 +1,104 @@
+#![cfg(all(
+    feature = "secret-heap",
+    any(
+        target_os = "linux",
+        target_os = "android",
+        target_vendor = "apple",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "haiku"
+    )
+))]
+
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use support::filled_with_layout;
+use zeroizing_alloc::{SecretHeap, ZeroAlloc};
+
+#[test]
+fn recycled_blocks_are_wiped() {
+    let heap = SecretHeap::new(1 << 20);
+    let layout = Layout::from_size_align(48, 8).unwrap();
+    unsafe {
+        let a = filled_with_layout(&heap, layout, 0xAA);
+        let b = filled_with_layout(&heap, layout, 0xBB);
+        heap.dealloc(a, layout);
+        heap.dealloc(b, layout);
+
+        // Free lists are LIFO, so both blocks come back
+        let c = heap.alloc(layout);
+        let d = heap.alloc(layout);
+        assert_eq!((c, d), (b, a));
+        assert_eq!(core::slice::from_raw_parts(c, 64), [0; 64]);
+        assert_eq!(core::slice::from_raw_parts(d, 64), [0; 64]);
+    }
+}
+
+#[test]
+fn blocks_are_aligned_and_disjoint() {
+    let heap = SecretHeap::new(1 << 20);
+    let mut blocks = Vec::new();
+    for (size, align) in [(1, 1), (24, 8), (100, 64), (4096, 4096), (5000, 16)] {
+        let layout = Layout::from_size_align(size, align).unwrap();
+        let ptr = unsafe { filled_with_layout(&heap, layout, 0xAA) };
+        assert_eq!(ptr.addr() % align, 0);
+        blocks.push((ptr.addr(), size));
+    }
+    blocks.sort();
+    assert!(blocks.windows(2).all(|w| w[0].0 + w[0].1 <= w[1].0));
+}
+
+#[test]
+fn exhausted_region_returns_null() {
+    let heap = SecretHeap::new(64 * 1024);
+    let layout = Layout::from_size_align(32 * 1024, 8).unwrap();
+    unsafe {
+        assert!(!heap.alloc(layout).is_null());
+        assert!(!heap.alloc(layout).is_null());
+        assert!(heap.alloc(layout).is_null());
+        assert!(heap
+            .alloc(Layout::from_size_align(64, 8192 * 1024).unwrap())
+            .is_null());
+    }
+}
+
+#[test]
+fn wipe_all_takes_every_block_back() {
+    let heap = SecretHeap::new(1 << 20);
+    let layout = Layout::from_size_align(256, 8).unwrap();
+    unsafe {
+        let first = filled_with_layout(&heap, layout, 0xAA);
+        filled_with_layout(&heap, layout, 0xBB);
+        heap.wipe_all();
+
+        let again = heap.alloc(layout);
+        assert_eq!(again, first);
+        assert_eq!(core::slice::from_raw_parts(again, 512), [0; 512]);
+    }
+}
+
+#[test]
+fn can_lock_and_exclude_from_dumps() {
+    let heap = SecretHeap::new(64 * 1024);
+    heap.lock().unwrap();
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    heap.exclude_from_dumps().unwrap();
+    unsafe { filled_with_layout(&heap, Layout::from_size_align(64, 8).unwrap(), 0xAA) };
+}
+
+#[test]
+fn works_as_inner_allocator() {
+    let alloc = ZeroAlloc::new(SecretHeap::new(1 << 20)).with_usable_size();
+    let layout = Layout::from_size_align(20, 4).unwrap();
+    unsafe {
+        let ptr = filled_with_layout(&alloc, layout, 0xAA);
+        let ptr = alloc.realloc(ptr, layout, 40);
+        assert_eq!(core::slice::from_raw_parts(ptr, 20), [0xAA; 20]);
+        alloc.dealloc(ptr, Layout::from_size_align(40, 4).unwrap());
+    }
+}
@@ -0,0 +1,93 @@
+#![cfg(feature = "selective")]
+
+mod support;
+
+use core::alloc::GlobalAlloc;
+use support::{filled, layout, released, InspectingAlloc};
+use zeroizing_alloc::{mark_secret, ZeroAlloc};
+
+fn selective() -> ZeroAlloc<InspectingAlloc> {
+    ZeroAlloc::new(InspectingAlloc::new()).with_selective_wiping()
+}
+
+#[test]
+fn only_marked_blocks_are_wiped() {
+    let alloc = selective();
+    unsafe {
+        let secret = filled(&alloc, 16, 0xAA);
+        let public = filled(&alloc, 16, 0xBB);
+        mark_secret(secret);
+        alloc.dealloc(secret, layout(16));
+        alloc.dealloc(public, layout(16));
+    }
+    assert_eq!(released(&alloc), [vec![0; 16], vec![0xBB; 16]]);
+}
+
+#[test]
+fn moved_blocks_stay_secret() {
+    let alloc = selective();
+    unsafe {
+        let secret = filled(&alloc, 16, 0xAA);
+        mark_secret(secret);
+        let moved = alloc.realloc(secret, layout(16), 32);
+        alloc.dealloc(moved, layout(32));
+    }
+    assert_eq!(released(&alloc), [vec![0; 16], vec![0; 32]]);
+}
+
+#[test]
+fn marks_are_forgotten_when_freed() {
+    // Marks more blocks than the table holds, one at a time
+    let marking = selective();
+    for _ in 0..=4096 {
+        unsafe {
+            let ptr = filled(&marking, 8, 0xAA);
+            mark_secret(ptr);
+            marking.dealloc(ptr, layout(8));
+        }
+    }
+
+    // Had the marks lingered, the table would have overflowed and this block would be wiped
+    let alloc = selective();
+    unsafe {
+        let public = filled(&alloc, 16, 0xBB);
+        alloc.dealloc(public, layout(16));
+    }
+    assert_eq!(released(&alloc), [vec![0xBB; 16]]);
+}
+
+#[cfg(feature = "std")]
+mod secure_scope {
+    use super::*;
+    use support::SharedAlloc;
+    use zeroizing_alloc::secure_scope;
+
+    #[test]
+    fn blocks_allocated_in_scope_are_wiped() {
+        let alloc = selective();
+        unsafe {
+            let secret = secure_scope(|| filled(&alloc, 16, 0xAA));
+            let public = filled(&alloc, 16, 0xBB);
+            // Freeing inside the scope doesn't make a block secret
+            secure_scope(|| alloc.dealloc(public, layout(16)));
+            alloc.dealloc(secret, layout(16));
+        }
+        assert_eq!(released(&alloc), [vec![0xBB; 16], vec![0; 16]]);
+    }
+
+    #[test]
+    fn blocks_freed_on_other_threads_are_wiped() {
+        let alloc = ZeroAlloc::new(SharedAlloc::new()).with_selective_wiping();
+        let secret = secure_scope(|| unsafe {
+            let ptr = alloc.alloc(layout(16));
+            ptr.write_bytes(0xAA, 16);
+            ptr.expose_provenance()
+        });
+        std::thread::scope(|s| {
+            s.spawn(|| unsafe {
+                alloc.dealloc(core::ptr::with_exposed_provenance_mut(secret), layout(16))
+            });
+        });
+        assert_eq!(alloc.inner().released(), [vec![0; 16]]);
+    }
+}
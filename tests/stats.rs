@@ -0,0 +1,45 @@
+#![cfg(feature = "stats")]
+
+use core::alloc::{GlobalAlloc, Layout};
+use std::alloc::System;
+use zeroizing_alloc::{Stats, ZeroAlloc};
+
+#[global_allocator]
+static ALLOC: ZeroAlloc<System> = ZeroAlloc::new(System);
+
+#[test]
+fn global_allocator_is_live() {
+    let before = ALLOC.stats();
+    drop(core::hint::black_box(vec![0xAAu8; 4096]));
+    let after = ALLOC.stats();
+
+    assert!(after.allocations > before.allocations);
+    assert!(after.deallocations > before.deallocations);
+    assert!(after.bytes_wiped >= before.bytes_wiped + 4096);
+    assert!(after.largest_wipe >= 4096);
+}
+
+#[test]
+fn counts_every_operation() {
+    let alloc = ZeroAlloc::new(System).with_max_size(64);
+    let small = Layout::from_size_align(16, 8).unwrap();
+    let large = Layout::from_size_align(128, 8).unwrap();
+    unsafe {
+        let a = alloc.alloc(small);
+        let b = alloc.alloc_zeroed(large);
+        let a = alloc.realloc(a, small, 32);
+        alloc.dealloc(a, Layout::from_size_align(32, 8).unwrap());
+        alloc.dealloc(b, large);
+    }
+
+    assert_eq!(
+        alloc.stats(),
+        Stats {
+            allocations: 3,
+            deallocations: 3,
+            bytes_wiped: 16 + 32,
+            largest_wipe: 32,
+            realloc_moves: 1,
+        }
+    );
+}
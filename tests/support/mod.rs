@@ -0,0 +1,242 @@
+//! An inner allocator that lets tests look at exactly what `ZeroAlloc` hands back to it.
+#![allow(dead_code)]
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::{Cell, RefCell, UnsafeCell};
+use std::process::{Command, Output};
+use std::sync::Mutex;
+use zeroizing_alloc::{ResizeInPlace, UsableSize, ZeroAlloc};
+
+pub const ARENA_SIZE: usize = 64 * 1024;
+
+/// Bytes the arena starts out with, standing in for stale data left by earlier owners.
+pub const STALE: u8 = 0xFF;
+
+#[repr(C, align(4096))]
+struct Arena(UnsafeCell<[u8; ARENA_SIZE]>);
+
+/// Bump allocator over a fixed arena that snapshots every block at the moment it is handed back.
+///
+/// Memory is never reused, so snapshots are taken from memory the arena still owns rather than by reading freed memory,
+/// which would be UB. Resizing in place is supported for shrinks and for growing the most recent block.
+pub struct InspectingAlloc {
+    arena: Box<Arena>,
+    next: Cell<usize>,
+    granule: usize,
+    released: RefCell<Vec<Vec<u8>>>,
+}
+
+impl InspectingAlloc {
+    pub fn new() -> Self {
+        Self::with_granule(1)
+    }
+
+    /// Rounds every block up to a multiple of `granule`, reporting (and snapshotting) the slack through `UsableSize`.
+    pub fn with_granule(granule: usize) -> Self {
+        Self {
+            arena: Box::new(Arena(UnsafeCell::new([STALE; ARENA_SIZE]))),
+            next: Cell::new(0),
+            granule,
+            released: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns the contents of every block (or truncated tail) handed back so far, in order.
+    pub fn released(&self) -> Vec<Vec<u8>> {
+        self.released.borrow().clone()
+    }
+
+    /// Asserts that something was handed back, and that every byte of it was wiped to `pattern`.
+    pub fn assert_wiped(&self, pattern: u8) {
+        let released = self.released.borrow();
+        assert!(!released.is_empty(), "nothing was handed back");
+        for (i, block) in released.iter().enumerate() {
+            if let Some(offset) = block.iter().position(|&b| b != pattern) {
+                panic!(
+                    "block {i} ({} bytes) was handed back with byte {offset} set to {:#04x}",
+                    block.len(),
+                    block[offset]
+                );
+            }
+        }
+    }
+
+    fn span(&self, size: usize) -> usize {
+        size.next_multiple_of(self.granule)
+    }
+
+    fn base(&self) -> *mut u8 {
+        self.arena.0.get().cast()
+    }
+
+    unsafe fn record(&self, ptr: *mut u8, len: usize) {
+        let bytes = core::slice::from_raw_parts(ptr, len).to_vec();
+        self.released.borrow_mut().push(bytes);
+    }
+}
+
+unsafe impl GlobalAlloc for InspectingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let start = self.next.get().next_multiple_of(layout.align());
+        if start + self.span(layout.size()) > ARENA_SIZE {
+            return core::ptr::null_mut();
+        }
+        self.next.set(start + self.span(layout.size()));
+        self.base().add(start)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.record(ptr, self.span(layout.size()));
+    }
+}
+
+// Lets several allocators be stacked over one arena, so their blocks share pages
+unsafe impl GlobalAlloc for &InspectingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        (**self).alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        (**self).dealloc(ptr, layout)
+    }
+}
+
+unsafe impl ResizeInPlace for InspectingAlloc {
+    unsafe fn can_resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        if new_size <= layout.size() {
+            return true;
+        }
+
+        // Only the most recent block can grow, and only while the arena has room.
+        let start = ptr.offset_from(self.base()) as usize;
+        start + self.span(layout.size()) == self.next.get()
+            && start + self.span(new_size) <= ARENA_SIZE
+    }
+
+    unsafe fn resize_in_place(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> bool {
+        if !self.can_resize_in_place(ptr, layout, new_size) {
+            return false;
+        }
+        if new_size <= layout.size() {
+            self.record(ptr.add(new_size), self.span(layout.size()) - new_size);
+        } else {
+            let start = ptr.offset_from(self.base()) as usize;
+            self.next.set(start + self.span(new_size));
+        }
+        true
+    }
+}
+
+unsafe impl UsableSize for InspectingAlloc {
+    unsafe fn usable_size(&self, _ptr: *mut u8, layout: Layout) -> usize {
+        self.span(layout.size())
+    }
+}
+
+/// An [`InspectingAlloc`] behind a lock, for tests which free blocks on other threads.
+pub struct SharedAlloc(pub Mutex<InspectingAlloc>);
+
+impl SharedAlloc {
+    pub fn new() -> Self {
+        Self(Mutex::new(InspectingAlloc::new()))
+    }
+
+    pub fn released(&self) -> Vec<Vec<u8>> {
+        self.0.lock().unwrap().released()
+    }
+}
+
+unsafe impl GlobalAlloc for SharedAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0.lock().unwrap().alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().unwrap().dealloc(ptr, layout)
+    }
+}
+
+/// Returns the `VmFlags` of the mapping containing `ptr`, e.g. `lo` for `mlock`, `dd` for `MADV_DONTDUMP` and `wf` for
+/// `MADV_WIPEONFORK`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn vm_flags(ptr: *mut u8) -> Vec<String> {
+    let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+    let mut inside = false;
+    for line in smaps.lines() {
+        if let Some((range, _)) = line.split_once(' ') {
+            if let Some((start, end)) = range.split_once('-') {
+                if let (Ok(start), Ok(end)) = (
+                    usize::from_str_radix(start, 16),
+                    usize::from_str_radix(end, 16),
+                ) {
+                    inside = (start..end).contains(&ptr.addr());
+                    continue;
+                }
+            }
+        }
+        if let Some(flags) = line.strip_prefix("VmFlags:") {
+            if inside {
+                return flags.split_whitespace().map(String::from).collect();
+            }
+        }
+    }
+    panic!("no mapping contains {ptr:p}");
+}
+
+/// Returns the layout of a block of `size` bytes aligned to 8, as most tests use.
+pub fn layout(size: usize) -> Layout {
+    Layout::from_size_align(size, 8).unwrap()
+}
+
+/// Allocates `size` bytes aligned to 8 from `alloc` and fills them with `byte`.
+pub unsafe fn filled(alloc: &impl GlobalAlloc, size: usize, byte: u8) -> *mut u8 {
+    filled_with_layout(alloc, layout(size), byte)
+}
+
+/// Allocates a block of `layout` from `alloc` and fills it with `byte`.
+pub unsafe fn filled_with_layout(alloc: &impl GlobalAlloc, layout: Layout, byte: u8) -> *mut u8 {
+    let ptr = alloc.alloc(layout);
+    assert!(!ptr.is_null());
+    ptr.write_bytes(byte, layout.size());
+    ptr
+}
+
+/// Returns the contents of every block `alloc` handed back to its inner allocator so far.
+pub fn released<W>(alloc: &ZeroAlloc<InspectingAlloc, W>) -> Vec<Vec<u8>> {
+    alloc.inner().released()
+}
+
+/// Set in the environment of the processes started by [`run_in_child`].
+const CHILD: &str = "ZEROIZING_ALLOC_TEST_CHILD";
+
+/// Returns whether this process was started by [`run_in_child`], to run a single test.
+pub fn is_child() -> bool {
+    std::env::var_os(CHILD).is_some()
+}
+
+/// Runs `test` alone in a child process, e.g. to check how it dies, returning its status and output.
+pub fn run_in_child(test: &str) -> Output {
+    Command::new(std::env::current_exe().unwrap())
+        .args([test, "--exact", "--test-threads=1"])
+        .env(CHILD, "1")
+        .output()
+        .unwrap()
+}
+
+/// Runs `test` in a child process of its own, for tests relying on process-wide state that other tests would disturb.
+///
+/// Returns `true` in the child, which goes on to run the test, and `false` once the child passed.
+pub fn isolated(test: &str) -> bool {
+    if is_child() {
+        return true;
+    }
+    let output = run_in_child(test);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{test} failed in its own process:\n{stdout}"
+    );
+    // A misspelt name would otherwise pass by running nothing
+    assert!(stdout.contains("1 passed"), "{test} didn't run:\n{stdout}");
+    false
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "std")]
+
+zeroizing_alloc::zeroizing_global_allocator!(ALLOC);
+
+#[test]
+fn can_alloc() {
+    let mut allocation = core::hint::black_box(Vec::<u8>::with_capacity(16));
+    allocation.extend_from_slice(&[0xAA; 16]);
+    allocation.resize(4096, 0xBB);
+    assert!(allocation[..16].iter().all(|&b| b == 0xAA));
+    drop(allocation); // Cannot check if zeroed post-drop without UB
+}
+
+#[cfg(feature = "stats")]
+#[test]
+fn global_allocator_is_live() {
+    let before = ALLOC.stats();
+    drop(core::hint::black_box(vec![0xAAu8; 4096]));
+    assert!(ALLOC.stats().bytes_wiped >= before.bytes_wiped + 4096);
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn system_reports_usable_size() {
+    use core::alloc::{GlobalAlloc, Layout};
+    use std::alloc::System;
+    use zeroizing_alloc::{UsableSize, ZeroAlloc};
+
+    let alloc = ZeroAlloc::new(System).with_usable_size();
+    let layout = Layout::from_size_align(20, 8).unwrap();
+    unsafe {
+        let ptr = alloc.alloc(layout);
+        let usable = System.usable_size(ptr, layout);
+        assert!(usable >= layout.size());
+        // Writing up to the usable size is allowed, and is what leaves secrets in the slack
+        ptr.write_bytes(0xAA, usable);
+        alloc.dealloc(ptr, layout);
+        #[cfg(feature = "stats")]
+        assert_eq!(alloc.stats().bytes_wiped, usable);
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[test]
+fn system_grows_in_place_within_usable_size() {
+    use core::alloc::{GlobalAlloc, Layout};
+    use std::alloc::System;
+    use zeroizing_alloc::{UsableSize, ZeroAlloc};
+
+    let alloc = ZeroAlloc::new(System).with_resize_in_place();
+    let layout = Layout::from_size_align(20, 8).unwrap();
+    unsafe {
+        let ptr = alloc.alloc(layout);
+        ptr.write_bytes(0xAA, layout.size());
+        let usable = System.usable_size(ptr, layout);
+        let grown = alloc.realloc(ptr, layout, usable);
+        assert_eq!(grown, ptr);
+        assert_eq!(core::slice::from_raw_parts(grown, 20), [0xAA; 20]);
+
+        // Growing past the usable size moves the block
+        let moved = alloc.realloc(
+            grown,
+            Layout::from_size_align(usable, 8).unwrap(),
+            usable + 4096,
+        );
+        assert_ne!(moved, grown);
+        assert_eq!(core::slice::from_raw_parts(moved, 20), [0xAA; 20]);
+        alloc.dealloc(moved, Layout::from_size_align(usable + 4096, 8).unwrap());
+    }
+}
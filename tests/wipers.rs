@@ -0,0 +1,74 @@
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use support::InspectingAlloc;
+use zeroizing_alloc::{FnPtrMemset, VolatileLoop, Wiper, ZeroAlloc};
+
+fn wipers() -> Vec<(&'static str, &'static dyn Wiper)> {
+    let mut wipers: Vec<(&'static str, &'static dyn Wiper)> = vec![
+        ("VolatileLoop", &VolatileLoop),
+        ("FnPtrMemset", &FnPtrMemset),
+    ];
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "dragonfly"
+    ))]
+    wipers.push(("ExplicitBzero", &zeroizing_alloc::ExplicitBzero));
+    #[cfg(target_vendor = "apple")]
+    wipers.push(("MemsetS", &zeroizing_alloc::MemsetS));
+    #[cfg(target_arch = "x86_64")]
+    wipers.push((
+        "NonTemporal",
+        &const { zeroizing_alloc::NonTemporal::with_threshold(0) },
+    ));
+    wipers
+}
+
+#[test]
+fn wipers_clear_every_byte() {
+    for (name, wiper) in wipers() {
+        for pattern in [0x00, 0xDE] {
+            let mut buf = [0xAAu8; 1027];
+            unsafe { wiper.wipe(buf.as_mut_ptr().add(1), 1025, pattern) };
+            assert_eq!(buf[0], 0xAA, "{name} wrote before the range");
+            assert!(
+                buf[1..1026].iter().all(|&b| b == pattern),
+                "{name} left bytes behind"
+            );
+            assert_eq!(buf[1026], 0xAA, "{name} wrote past the range");
+        }
+    }
+}
+
+#[test]
+fn can_pick_wiper_per_allocator() {
+    let reference = ZeroAlloc::with_wiper(InspectingAlloc::new(), VolatileLoop);
+    let fast = ZeroAlloc::with_wiper(InspectingAlloc::new(), FnPtrMemset);
+    let layout = Layout::from_size_align(256, 8).unwrap();
+    unsafe {
+        let a = reference.alloc(layout);
+        let b = fast.alloc(layout);
+        assert!(!a.is_null() && !b.is_null());
+        a.write_bytes(0xAA, layout.size());
+        b.write_bytes(0xBB, layout.size());
+        reference.dealloc(a, layout);
+        fast.dealloc(b, layout);
+    }
+    reference.inner().assert_wiped(0);
+    fast.inner().assert_wiped(0);
+}
+
+#[cfg(all(
+    feature = "libc-wipe",
+    target_os = "linux",
+    not(feature = "reference_impl")
+))]
+#[test]
+fn libc_wipe_is_default_on_linux() {
+    assert_eq!(
+        core::any::type_name::<zeroizing_alloc::DefaultWiper>(),
+        core::any::type_name::<zeroizing_alloc::ExplicitBzero>()
+    );
+}
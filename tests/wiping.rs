@@ -0,0 +1,124 @@
+//! Proves, through `InspectingAlloc`, that every byte handed back to the inner allocator was wiped.
+
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use support::{released, InspectingAlloc, STALE};
+use zeroizing_alloc::{DefaultWiper, FnPtrMemset, VolatileLoop, Wiper, ZeroAlloc};
+
+// Frees blocks of assorted sizes and alignments, directly and through `realloc` moves and in-place shrinks.
+fn exercise<W: Wiper>(alloc: &ZeroAlloc<InspectingAlloc, W>) {
+    for (size, align) in [
+        (1, 1),
+        (7, 1),
+        (8, 8),
+        (63, 16),
+        (64, 64),
+        (1000, 8),
+        (4096, 4096),
+    ] {
+        let layout = Layout::from_size_align(size, align).unwrap();
+        unsafe {
+            let ptr = alloc.alloc(layout);
+            assert!(!ptr.is_null());
+            ptr.write_bytes(0xAA, size);
+
+            let grown = alloc.realloc(ptr, layout, size * 2);
+            assert!(!grown.is_null());
+            grown.write_bytes(0xBB, size * 2);
+
+            let shrunk = alloc.realloc(
+                grown,
+                Layout::from_size_align(size * 2, align).unwrap(),
+                size,
+            );
+            assert!(!shrunk.is_null());
+            alloc.dealloc(shrunk, layout);
+        }
+    }
+}
+
+fn assert_wipes<W: Wiper + Copy>(wiper: W) {
+    let alloc = ZeroAlloc::with_wiper(InspectingAlloc::new(), wiper).with_resize_in_place();
+    exercise(&alloc);
+    alloc.inner().assert_wiped(0);
+
+    // Without in-place resizing every `realloc` moves, handing back whole blocks instead of tails
+    let alloc = ZeroAlloc::with_wiper(InspectingAlloc::new(), wiper);
+    exercise(&alloc);
+    alloc.inner().assert_wiped(0);
+}
+
+#[test]
+fn reference_impl_wipes_everything() {
+    assert_wipes(VolatileLoop);
+}
+
+#[test]
+fn wiper_fn_ptr_wipes_everything() {
+    assert_wipes(FnPtrMemset);
+}
+
+#[test]
+fn default_wiper_wipes_everything() {
+    assert_wipes(DefaultWiper {});
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+#[test]
+fn explicit_bzero_wipes_everything() {
+    assert_wipes(zeroizing_alloc::ExplicitBzero);
+}
+
+#[test]
+fn pattern_fills_everything() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new())
+        .with_pattern(0xDE)
+        .with_resize_in_place();
+    exercise(&alloc);
+    alloc.inner().assert_wiped(0xDE);
+}
+
+#[test]
+fn harness_sees_unwiped_blocks() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new()).with_max_size(0);
+    exercise(&alloc);
+    let released = alloc.inner().released();
+    assert!(released
+        .iter()
+        .flatten()
+        .all(|&b| b == 0xAA || b == 0xBB || b == STALE));
+}
+
+#[test]
+fn usable_size_wipes_slack() {
+    let layout = Layout::from_size_align(20, 8).unwrap();
+    let write_usable = |alloc: &ZeroAlloc<InspectingAlloc>| unsafe {
+        let ptr = alloc.alloc(layout);
+        // Like code sizing its writes with `malloc_usable_size`
+        ptr.write_bytes(0xAA, 32);
+        alloc.dealloc(ptr, layout);
+    };
+
+    let alloc = ZeroAlloc::new(InspectingAlloc::with_granule(32)).with_usable_size();
+    write_usable(&alloc);
+    assert_eq!(released(&alloc), [vec![0; 32]]);
+
+    let alloc = ZeroAlloc::new(InspectingAlloc::with_granule(32));
+    write_usable(&alloc);
+    assert_eq!(released(&alloc), [[vec![0; 20], vec![0xAA; 12]].concat()]);
+}
+
+#[test]
+fn usable_size_wipes_slack_after_shrinking() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::with_granule(32))
+        .with_usable_size()
+        .with_resize_in_place();
+    exercise(&alloc);
+    alloc.inner().assert_wiped(0);
+}
@@ -0,0 +1,66 @@
+#![cfg(feature = "std")]
+
+mod support;
+
+use core::alloc::{GlobalAlloc, Layout};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use support::{filled, released, InspectingAlloc, SharedAlloc};
+use zeroizing_alloc::{without_wiping, ZeroAlloc};
+
+fn free(alloc: &ZeroAlloc<InspectingAlloc>, byte: u8) {
+    unsafe {
+        let ptr = filled(alloc, 16, byte);
+        alloc.dealloc(ptr, Layout::from_size_align(16, 8).unwrap());
+    }
+}
+
+#[test]
+fn wiping_resumes_after_scope() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new());
+    without_wiping(|| free(&alloc, 0xAA));
+    free(&alloc, 0xBB);
+    assert_eq!(released(&alloc), [vec![0xAA; 16], vec![0; 16]]);
+}
+
+#[test]
+fn nested_scopes_restore_outer_setting() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new());
+    without_wiping(|| {
+        without_wiping(|| free(&alloc, 0xAA));
+        free(&alloc, 0xBB);
+    });
+    free(&alloc, 0xCC);
+    assert_eq!(
+        released(&alloc),
+        [vec![0xAA; 16], vec![0xBB; 16], vec![0; 16]]
+    );
+}
+
+#[test]
+fn wiping_resumes_after_panic() {
+    let alloc = ZeroAlloc::new(InspectingAlloc::new());
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        without_wiping(|| {
+            free(&alloc, 0xAA);
+            panic!("unwinding out of the scope");
+        })
+    }));
+    assert!(result.is_err());
+    free(&alloc, 0xBB);
+    assert_eq!(released(&alloc), [vec![0xAA; 16], vec![0; 16]]);
+}
+
+#[test]
+fn blocks_freed_on_other_threads_are_wiped() {
+    let alloc = ZeroAlloc::new(SharedAlloc::new());
+    let layout = Layout::from_size_align(16, 8).unwrap();
+    let ptr = without_wiping(|| unsafe {
+        let ptr = alloc.alloc(layout);
+        ptr.write_bytes(0xAA, 16);
+        ptr.expose_provenance()
+    });
+    std::thread::scope(|s| {
+        s.spawn(|| unsafe { alloc.dealloc(core::ptr::with_exposed_provenance_mut(ptr), layout) });
+    });
+    assert_eq!(alloc.inner().released(), [vec![0; 16]]);
+}